@@ -1,9 +1,41 @@
+use chrono::Local;
 use crossterm::event::KeyEvent;
-use ratatui::widgets::ListState;
+use ratatui::widgets::{ListState, TableState};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, fs};
 use tui_textarea::TextArea;
 
-use crate::jobs::{get_cluster_overview, get_jobs, ClusterOverview, Job};
+use crate::backend::{get_jobs_from, get_partitions_from, Backend, Cluster};
+use crate::cli::Format;
+use crate::config::Config;
+use crate::form::Form;
+use crate::jobs::{
+    filter_jobs, filter_regex, get_cluster_overview, get_gpus, get_pending_reasons, is_finished,
+    update_start_estimates, ClusterOverview, GroupBy, Job, ReasonStats, StartEstimate,
+};
+use crate::output::{format_jobs, format_overview};
+use crate::partitions::Partition;
+use crate::steps::Step;
+use crate::templates::{load_templates, Template};
+use crate::usage::{get_mem_per_node, get_node_count, Efficiency, StepUsage};
 
+// sstat has to contact the nodes a job is running on,
+// so it is not run as often as squeue
+const USAGE_REFRESH_RATE: Duration = Duration::from_secs(10);
+// listing steps is another squeue call, or a sacct query once the
+// job has finished, which is too much to repeat on every tick
+const STEPS_REFRESH_RATE: Duration = Duration::from_secs(5);
+// output files are read with tail, which is cheap, and
+// worth watching closely while a job is running
+const LOG_REFRESH_RATE: Duration = Duration::from_secs(2);
+// node states change about as often as the queue does, but
+// nobody needs them that fresh
+const PARTITIONS_REFRESH_RATE: Duration = Duration::from_secs(10);
+// the per-user efficiency report covers a whole day of jobs
+const USER_EFFICIENCY_REFRESH_RATE: Duration = Duration::from_secs(300);
 
 pub enum Action {
     Quit,
@@ -18,84 +50,549 @@ pub enum Action {
     ResetView,
     ToggleFocus,
     ToggleOverview,
+    CycleGroupBy,
+    ToggleReasons,
+    TogglePartitions,
+    Select,
+    ToggleArray,
+    ToggleSteps,
+    ToggleUsage,
+    ToggleDependencies,
+    ToggleInfo,
+    ToggleScript,
+    ToggleStdout,
+    ToggleStderr,
+    EditJob,
+    ResubmitJob,
+    CancelJob,
+    CancelListedJobs,
+    HoldJob,
+    ReleaseJob,
+    ToggleTemplates,
+    Export(Format),
+    CycleCluster,
+    ToggleFinished,
+    NextField,
+    PreviousField,
+    SubmitForm,
     InputKey(KeyEvent),
+    FormInput(KeyEvent),
 }
 
 pub enum ViewState {
     Details,
     Help,
     Overview,
+    Reasons,
+    Partitions,
+    Templates,
+    Steps,
+    Usage,
+    Dependencies,
+    Info,
+    Script,
+    Log,
+}
+
+// A row in the jobs list. The tasks of a job array are collapsed into
+// a single row, which can be expanded to show them individually.
+pub enum JobRow {
+    Job(usize),
+    Array {
+        id: String,
+        tasks: Vec<usize>,
+        expanded: bool,
+    },
+    ArrayTask(usize),
+}
+
+// Arrays are told apart by cluster as well as ID, as job IDs on
+// different clusters are counted separately and can clash.
+fn build_rows(jobs: &[Job], expanded_arrays: &HashSet<(String, String)>) -> Vec<JobRow> {
+    let mut array_tasks: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (i, job) in jobs.iter().enumerate() {
+        if job.ArrayTaskID != "N/A" {
+            array_tasks
+                .entry((&job.Cluster, &job.ArrayJobID))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (i, job) in jobs.iter().enumerate() {
+        match array_tasks.get(&(job.Cluster.as_str(), job.ArrayJobID.as_str())) {
+            Some(tasks) if job.ArrayTaskID != "N/A" && tasks.len() > 1 => {
+                // the array goes where its first task would have been
+                if tasks[0] != i {
+                    continue;
+                }
+                let key = (job.Cluster.clone(), job.ArrayJobID.clone());
+                let expanded = expanded_arrays.contains(&key);
+                rows.push(JobRow::Array {
+                    id: job.ArrayJobID.clone(),
+                    tasks: tasks.clone(),
+                    expanded,
+                });
+                if expanded {
+                    rows.extend(tasks.iter().map(|t| JobRow::ArrayTask(*t)));
+                }
+            }
+            _ => rows.push(JobRow::Job(i)),
+        }
+    }
+    rows
+}
+
+// The sbatch options to resubmit a job with, labelled with the options
+// they are passed as. `info` is what scontrol show job says about it.
+fn resubmit_fields(job: &Job, info: &[(String, String)]) -> Vec<(String, String)> {
+    let info = |key: &str| {
+        info.iter()
+            .find(|(k, _)| k == key)
+            .map_or(String::new(), |(_, v)| v.clone())
+    };
+    let nodes = get_node_count(&job.TRES).unwrap_or(1);
+    // TRES has the job's GPUs in total, but --gres is per node
+    let gres: Vec<String> = get_gpus(&job.TRES)
+        .into_iter()
+        .map(|(gpu_type, n)| match gpu_type.as_str() {
+            "any" => format!("gpu:{}", n.div_ceil(nodes as u32)),
+            _ => format!("gpu:{}:{}", gpu_type, n.div_ceil(nodes as u32)),
+        })
+        .collect();
+    // memory is asked for either per node or per CPU
+    let mem = match (info("MinMemoryNode"), info("MinMemoryCPU")) {
+        (mem, _) if !mem.is_empty() => ("--mem", mem),
+        (_, mem) if !mem.is_empty() => ("--mem-per-cpu", mem),
+        _ => (
+            "--mem",
+            get_mem_per_node(&job.TRES).map_or(String::new(), |m| format!("{}M", m >> 20)),
+        ),
+    };
+    let array = match job.ArrayTaskID.as_str() {
+        "N/A" => String::new(),
+        task => task.to_string(),
+    };
+    vec![
+        ("--job-name", job.Name.clone()),
+        ("--partition", job.Partition.clone()),
+        ("--time", job.TimeLimit.clone()),
+        ("--nodes", nodes.to_string()),
+        ("--ntasks", job.NumTasks.clone()),
+        ("--cpus-per-task", info("CPUs/Task")),
+        mem,
+        ("--gres", gres.join(",")),
+        ("--qos", job.QOS.clone()),
+        ("--account", job.Account.clone()),
+        ("--chdir", job.WorkDir.clone()),
+        ("--output", output_pattern(&job.STDOUT, job)),
+        ("--error", output_pattern(&job.STDERR, job)),
+        ("--array", array),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+// The job's output file, with its ID turned back into a pattern so
+// that the new job doesn't write over it, e.g. slurm-123.out becomes
+// slurm-%j.out again. Only the file name is changed, as sbatch won't
+// create directories, e.g. for /scratch/run123/slurm-123.out.
+fn output_pattern(path: &str, job: &Job) -> String {
+    let (dir, file) = match path.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), path),
+    };
+    let file = match job.ArrayTaskID.as_str() {
+        "N/A" => replace_last(file, &job.JobID, "%j"),
+        task => replace_last(file, &format!("{}_{}", job.ArrayJobID, task), "%A_%a"),
+    };
+    dir + &file
+}
+
+fn replace_last(s: &str, from: &str, to: &str) -> String {
+    match s.rfind(from) {
+        Some(i) if !from.is_empty() => format!("{}{}{}", &s[..i], to, &s[i + from.len()..]),
+        _ => s.to_string(),
+    }
 }
 
 pub enum EditorState {
     Normal,
     Editing,
+    // filling in a form popup
+    Form,
+}
+
+// What to do with a form once it is submitted
+pub enum FormPurpose {
+    UpdateJob {
+        cluster: String,
+        job_id: String,
+    },
+    // sbatch options to submit this batch script with
+    Submit {
+        cluster: String,
+        script: String,
+    },
+    // values for the template's placeholders
+    SubmitTemplate {
+        cluster: String,
+        template: Template,
+    },
+    // confirmation to cancel these jobs, by cluster and job ID
+    Cancel {
+        jobs: Vec<(String, String)>,
+    },
+    // confirmation to hold (or release) a job or array
+    Hold {
+        cluster: String,
+        job_id: String,
+        hold: bool,
+    },
 }
 
 pub struct App<'a> {
+    pub clusters: Vec<Cluster>,
+    // with several clusters, 0 shows them all together,
+    // and i shows only the i-th (counting from 1)
+    pub cluster_tab: usize,
+    pub config: Config,
     pub should_quit: bool,
+    // whether to list jobs which have ended too
+    pub show_finished: bool,
+    // everything in the queue, and the jobs matching the filter
+    pub all_jobs: Vec<Job>,
     pub jobs: Vec<Job>,
+    // the jobs (and errors) being fetched in the background
+    jobs_pending: Option<Receiver<(Vec<Job>, Vec<String>)>>,
+    pub rows: Vec<JobRow>,
+    // keyed by cluster and array job ID
+    pub expanded_arrays: HashSet<(String, String)>,
     pub list_state: ListState,
     pub view_state: ViewState,
     pub text_area: TextArea<'a>,
     pub editor_state: EditorState,
     pub overview: ClusterOverview,
-
+    pub group_by: GroupBy,
+    pub reasons: Vec<ReasonStats>,
+    pub reason_state: TableState,
+    pub reason_filter: Option<String>,
+    // keyed by cluster and job ID
+    pub start_estimates: HashMap<(String, String), StartEstimate>,
+    // the job shown in the steps/usage/dependencies/info/script views
+    pub inspected_job_id: String,
+    pub inspected_cluster: String,
+    pub steps: Result<Vec<Step>, String>,
+    pub steps_refreshed: Instant,
+    pub usage: Result<Vec<StepUsage>, String>,
+    pub usage_refreshed: Instant,
+    // finished jobs don't change, so their efficiency is only fetched once
+    // (keyed by cluster and job ID)
+    pub efficiency: HashMap<(String, String), Result<Efficiency, String>>,
+    pub user_efficiency: Result<HashMap<String, Efficiency>, String>,
+    pub user_efficiency_refreshed: Option<Instant>,
+    // sacct can take a while to go through a day of everyone's jobs,
+    // so the report is fetched in the background
+    user_efficiency_pending: Option<Receiver<Result<HashMap<String, Efficiency>, String>>>,
+    pub partitions: Result<Vec<Partition>, String>,
+    pub partitions_refreshed: Instant,
+    pub job_info: Result<Vec<(String, String)>, String>,
+    pub batch_script: Result<String, String>,
+    // the end of the inspected job's STDOUT or STDERR, from this path
+    pub log: Result<String, String>,
+    pub log_path: String,
+    pub log_refreshed: Instant,
+    // how far the info/script views are scrolled down,
+    // or the log view up from the end
+    pub panel_scroll: u16,
+    pub form: Option<(FormPurpose, Form<'a>)>,
+    // the outcome of the last thing the user asked Slurm to do
+    pub status: Option<Result<String, String>>,
+    // why the jobs could not be listed last time, if they couldn't
+    jobs_error: Option<String>,
+    // a newly submitted job for the cursor to jump to once squeue lists
+    // it, and the cluster it was submitted to
+    pub jump_to: Option<(String, String)>,
+    pub templates: Result<Vec<Template>, String>,
+    pub template_state: ListState,
 }
 
-impl App<'_> {
-    pub fn new() -> Self {
+impl<'a> App<'a> {
+    pub fn new(config: Config, clusters: Vec<Cluster>) -> Self {
         let text_area = TextArea::default();
         let mut list_state = ListState::default();
-        let jobs = get_jobs(&"".to_string());
-        if !jobs.is_empty() {
+        let (all_jobs, errors) = get_jobs_from(&clusters.iter().collect::<Vec<_>>(), false);
+        let jobs_error = match errors.is_empty() {
+            true => None,
+            false => Some(errors.join("; ")),
+        };
+        let status = jobs_error.clone().map(Err);
+        let jobs = all_jobs.clone();
+        let expanded_arrays = HashSet::new();
+        let rows = build_rows(&jobs, &expanded_arrays);
+        if !rows.is_empty() {
             list_state.select(Some(0));
         }
-        let overview = get_cluster_overview(&jobs);
+        let group_by = GroupBy::default();
+        let overview = get_cluster_overview(&jobs, group_by);
+        let reasons = get_pending_reasons(&jobs);
+        let mut start_estimates = HashMap::new();
+        update_start_estimates(&mut start_estimates, &all_jobs);
 
         App {
+            clusters,
+            cluster_tab: 0,
+            config,
+            show_finished: false,
+            all_jobs,
             jobs,
+            jobs_pending: None,
+            rows,
+            expanded_arrays,
             list_state,
             text_area,
             should_quit: false,
             view_state: ViewState::Overview,
             editor_state: EditorState::Normal,
-            overview
+            overview,
+            group_by,
+            reasons,
+            reason_state: TableState::default(),
+            reason_filter: None,
+            start_estimates,
+            inspected_job_id: String::new(),
+            inspected_cluster: String::new(),
+            steps: Ok(vec![]),
+            steps_refreshed: Instant::now(),
+            usage: Ok(vec![]),
+            usage_refreshed: Instant::now(),
+            efficiency: HashMap::new(),
+            user_efficiency: Ok(HashMap::new()),
+            user_efficiency_refreshed: None,
+            user_efficiency_pending: None,
+            partitions: Ok(vec![]),
+            partitions_refreshed: Instant::now(),
+            job_info: Ok(vec![]),
+            batch_script: Ok(String::new()),
+            log: Ok(String::new()),
+            log_path: String::new(),
+            log_refreshed: Instant::now(),
+            panel_scroll: 0,
+            form: None,
+            status,
+            jobs_error,
+            jump_to: None,
+            templates: Ok(vec![]),
+            template_state: ListState::default(),
         }
     }
 
-    pub fn update(&mut self, action: Option<Action>) -> () {
+    pub fn update(&mut self, action: Option<Action>) {
         match action {
             Some(Action::Quit) => self.should_quit = true,
             Some(Action::Tick) => self.tick(),
-            Some(Action::Up) => self.previous(),
-            Some(Action::Down) => self.next(),
+            Some(Action::Up) => match self.view_state {
+                ViewState::Reasons => self.previous_reason(),
+                ViewState::Templates => self.previous_template(),
+                ViewState::Info | ViewState::Script | ViewState::Log | ViewState::Help => {
+                    self.scroll_panel(-1)
+                }
+                _ => self.previous(),
+            },
+            Some(Action::Down) => match self.view_state {
+                ViewState::Reasons => self.next_reason(),
+                ViewState::Templates => self.next_template(),
+                ViewState::Info | ViewState::Script | ViewState::Log | ViewState::Help => {
+                    self.scroll_panel(1)
+                }
+                _ => self.next(),
+            },
             Some(Action::Home) => self.home(),
             Some(Action::End) => self.end(),
-            Some(Action::PageDown) => self.down_5(),
-            Some(Action::PageUp) => self.up_5(),
+            Some(Action::PageDown) => match self.view_state {
+                ViewState::Info | ViewState::Script | ViewState::Log | ViewState::Help => {
+                    self.scroll_panel(5)
+                }
+                _ => self.down_5(),
+            },
+            Some(Action::PageUp) => match self.view_state {
+                ViewState::Info | ViewState::Script | ViewState::Log | ViewState::Help => {
+                    self.scroll_panel(-5)
+                }
+                _ => self.up_5(),
+            },
             Some(Action::ToggleHelp) => self.toggle_help(),
             Some(Action::ResetView) => self.reset_view(),
             Some(Action::ToggleOverview) => self.toggle_overview(),
+            Some(Action::CycleGroupBy) => self.cycle_group_by(),
+            Some(Action::ToggleReasons) => self.toggle_reasons(),
+            Some(Action::TogglePartitions) => self.toggle_partitions(),
+            Some(Action::Select) => self.select(),
+            Some(Action::ToggleArray) => self.toggle_array(),
+            Some(Action::ToggleSteps) => self.toggle_steps(),
+            Some(Action::ToggleUsage) => self.toggle_usage(),
+            Some(Action::ToggleDependencies) => self.toggle_dependencies(),
+            Some(Action::ToggleInfo) => self.toggle_info(),
+            Some(Action::ToggleScript) => self.toggle_script(),
+            Some(Action::ToggleStdout) => self.toggle_log(false),
+            Some(Action::ToggleStderr) => self.toggle_log(true),
+            Some(Action::EditJob) => self.edit_job(),
+            Some(Action::ResubmitJob) => self.resubmit_job(),
+            Some(Action::CancelJob) => self.cancel_job(),
+            Some(Action::CancelListedJobs) => self.cancel_listed_jobs(),
+            Some(Action::HoldJob) => self.hold_job(true),
+            Some(Action::ReleaseJob) => self.hold_job(false),
+            Some(Action::ToggleTemplates) => self.toggle_templates(),
+            Some(Action::Export(format)) => self.export(format),
+            Some(Action::CycleCluster) => self.cycle_cluster(),
+            Some(Action::ToggleFinished) => self.toggle_finished(),
+            Some(Action::NextField) => self.form.iter_mut().for_each(|(_, f)| f.next_field()),
+            Some(Action::PreviousField) => {
+                self.form.iter_mut().for_each(|(_, f)| f.previous_field())
+            }
+            Some(Action::SubmitForm) => self.submit_form(),
+            Some(Action::FormInput(key)) => self.form.iter_mut().for_each(|(_, f)| f.input(key)),
             Some(Action::ToggleFocus) => self.toggle_focus(),
             Some(Action::InputKey(key)) => self.text_input(key),
             None => (),
         }
     }
 
-    pub fn tick(&mut self) -> () {
-        self.jobs = get_jobs(&self.text_area.lines().concat());
-        self.overview = get_cluster_overview(&self.jobs);
+    // Slurm can be slow to answer (squeue when slurmctld is busy, or
+    // slurmrestd over a slow network), so jobs are fetched in the
+    // background, one lot at a time, and shown on the tick after they
+    // arrive.
+    pub fn tick(&mut self) {
+        match self.jobs_pending.as_ref().map(|pending| pending.try_recv()) {
+            Some(Ok((jobs, errors))) => {
+                self.jobs_pending = None;
+                self.set_jobs(jobs, errors);
+            }
+            Some(Err(TryRecvError::Empty)) => (),
+            Some(Err(TryRecvError::Disconnected)) | None => {
+                let clusters: Vec<Cluster> = self.visible_clusters().into_iter().cloned().collect();
+                let finished = self.show_finished;
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let clusters: Vec<&Cluster> = clusters.iter().collect();
+                    let _ = sender.send(get_jobs_from(&clusters, finished));
+                });
+                self.jobs_pending = Some(receiver);
+            }
+        }
+        self.update_jobs();
+    }
+
+    // Fetch the jobs straight away, after something which changes them
+    pub fn reload_jobs(&mut self) {
+        // anything on its way is out of date now
+        self.jobs_pending = None;
+        let (jobs, errors) = get_jobs_from(&self.visible_clusters(), self.show_finished);
+        self.set_jobs(jobs, errors);
+        self.update_jobs();
+    }
+
+    fn set_jobs(&mut self, jobs: Vec<Job>, errors: Vec<String>) {
+        // keep showing the last jobs seen if Slurm can't be reached
+        if errors.is_empty() || !jobs.is_empty() {
+            self.all_jobs = jobs;
+        }
+        // once every cluster answers again, the error goes away,
+        // unless something else has been reported since
+        let shown = self.status.as_ref().and_then(|s| s.as_ref().err());
+        if errors.is_empty() && self.jobs_error.is_some() && shown == self.jobs_error.as_ref() {
+            self.status = None;
+        }
+        self.jobs_error = match errors.is_empty() {
+            true => None,
+            false => Some(errors.join("; ")),
+        };
+        if let Some(e) = &self.jobs_error {
+            self.status = Some(Err(e.clone()));
+        }
+    }
+
+    // Filter and summarise the jobs, and keep the views up to date
+    fn update_jobs(&mut self) {
+        self.jobs = filter_jobs(&self.all_jobs, &self.text_area.lines().concat());
+        self.reasons = get_pending_reasons(&self.jobs);
+        update_start_estimates(&mut self.start_estimates, &self.all_jobs);
+        if let Some(reason) = &self.reason_filter {
+            self.jobs
+                .retain(|j| j.State == "PENDING" && &j.Reason == reason);
+        }
+        self.overview = get_cluster_overview(&self.jobs, self.group_by);
+        self.rows = build_rows(&self.jobs, &self.expanded_arrays);
+        match self.view_state {
+            ViewState::Steps if self.steps_refreshed.elapsed() >= STEPS_REFRESH_RATE => {
+                self.refresh_steps()
+            }
+            ViewState::Usage if self.usage_refreshed.elapsed() >= USAGE_REFRESH_RATE => {
+                self.refresh_usage()
+            }
+            ViewState::Log if self.log_refreshed.elapsed() >= LOG_REFRESH_RATE => {
+                self.refresh_log()
+            }
+            ViewState::Partitions
+                if self.partitions_refreshed.elapsed() >= PARTITIONS_REFRESH_RATE =>
+            {
+                self.refresh_partitions()
+            }
+            ViewState::Details => self.load_efficiency(),
+            ViewState::Overview if self.group_by == GroupBy::User => {
+                let stale = self
+                    .user_efficiency_refreshed
+                    .is_none_or(|t| t.elapsed() >= USER_EFFICIENCY_REFRESH_RATE);
+                if stale && self.user_efficiency_pending.is_none() {
+                    self.refresh_user_efficiency();
+                }
+            }
+            _ => (),
+        }
+        if let Some(result) = self
+            .user_efficiency_pending
+            .as_ref()
+            .and_then(|pending| pending.try_recv().ok())
+        {
+            self.user_efficiency = result;
+            self.user_efficiency_pending = None;
+        }
+
+        match self.reason_state.selected() {
+            Some(_) if self.reasons.is_empty() => self.reason_state.select(None),
+            Some(i) if i >= self.reasons.len() => {
+                self.reason_state.select(Some(self.reasons.len() - 1))
+            }
+            None if !self.reasons.is_empty() => self.reason_state.select(Some(0)),
+            _ => (),
+        }
+
+        if let Some((cluster, job_id)) = &self.jump_to {
+            // a lone cluster has no name, whatever squeue calls it
+            let on_cluster = |i: &usize| cluster.is_empty() || &self.jobs[*i].Cluster == cluster;
+            let row = self.rows.iter().position(|row| match row {
+                JobRow::Job(i) | JobRow::ArrayTask(i) => {
+                    &self.jobs[*i].JobID == job_id && on_cluster(i)
+                }
+                JobRow::Array { id, tasks, .. } => id == job_id && on_cluster(&tasks[0]),
+            });
+            if let Some(row) = row {
+                self.list_state.select(Some(row));
+                self.jump_to = None;
+            }
+        }
 
         // prevent list from pointing to a job out of range
         // e.g. if the cursor is on the last job and one is cancelled
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None)
         } else {
             match self.list_state.selected() {
                 None => self.home(),
                 Some(i) => {
-                    if i > self.jobs.len() - 1 {
+                    if i > self.rows.len() - 1 {
                         self.end()
                     }
                 }
@@ -103,23 +600,731 @@ impl App<'_> {
         }
     }
 
-    pub fn toggle_overview(&mut self) -> () {
+    pub fn visible_clusters(&self) -> Vec<&Cluster> {
+        match self.cluster_tab {
+            0 => self.clusters.iter().collect(),
+            i => vec![&self.clusters[i - 1]],
+        }
+    }
+
+    // The backend for a job's cluster, as named in its Cluster field.
+    // With only one cluster, that is the one whatever the name.
+    fn backend(&self, cluster: &str) -> &dyn Backend {
+        let cluster = self
+            .clusters
+            .iter()
+            .find(|c| c.name == cluster)
+            .unwrap_or(&self.clusters[0]);
+        cluster.backend.as_ref()
+    }
+
+    // Where new jobs go: the cluster being shown, or
+    // the first one when they are all shown together
+    fn submit_cluster(&self) -> String {
+        self.visible_clusters()[0].name.clone()
+    }
+
+    pub fn cycle_cluster(&mut self) {
+        if self.clusters.len() < 2 {
+            return;
+        }
+        self.cluster_tab = (self.cluster_tab + 1) % (self.clusters.len() + 1);
+        self.user_efficiency_refreshed = None;
+        // a report on the clusters shown before would be out of place
+        self.user_efficiency_pending = None;
+        self.reload_jobs();
+        if let ViewState::Partitions = self.view_state {
+            self.refresh_partitions();
+        }
+    }
+
+    // Jobs which have ended are only listed while Slurm remembers them,
+    // which is long enough to check how efficient they were, or to
+    // resubmit them after a timeout.
+    pub fn toggle_finished(&mut self) {
+        self.show_finished = !self.show_finished;
+        self.status = Some(Ok(match self.show_finished {
+            true => "Showing finished jobs".to_string(),
+            false => "Hiding finished jobs".to_string(),
+        }));
+        self.reload_jobs();
+    }
+
+    fn refresh_user_efficiency(&mut self) {
+        let backends: Vec<_> = self
+            .visible_clusters()
+            .iter()
+            .map(|c| c.backend.clone())
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut user_efficiency: HashMap<String, Efficiency> = HashMap::new();
+            let mut errors = vec![];
+            for backend in &backends {
+                match backend.get_user_efficiency() {
+                    Ok(efficiency) => {
+                        for (user, e) in efficiency {
+                            user_efficiency.entry(user).or_default().add(&e);
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
+            let result = match errors.len() == backends.len() {
+                true => Err(errors.join("; ")),
+                false => Ok(user_efficiency),
+            };
+            // the app may have moved on to other clusters in the meantime
+            let _ = sender.send(result);
+        });
+        self.user_efficiency_pending = Some(receiver);
+        self.user_efficiency_refreshed = Some(Instant::now());
+    }
+
+    pub fn toggle_overview(&mut self) {
         match self.view_state {
             ViewState::Overview => self.view_state = ViewState::Details,
             _ => self.view_state = ViewState::Overview,
         }
     }
 
-    pub fn next(&mut self) -> () {
+    pub fn cycle_group_by(&mut self) {
+        self.view_state = ViewState::Overview;
+        self.group_by = self.group_by.next();
+        self.overview = get_cluster_overview(&self.jobs, self.group_by);
+    }
+
+    pub fn toggle_reasons(&mut self) {
+        match self.view_state {
+            ViewState::Reasons => self.view_state = ViewState::Details,
+            _ => {
+                self.view_state = ViewState::Reasons;
+                if self.reason_state.selected().is_none() && !self.reasons.is_empty() {
+                    self.reason_state.select(Some(0));
+                }
+            }
+        }
+    }
+
+    pub fn toggle_partitions(&mut self) {
+        match self.view_state {
+            ViewState::Partitions => self.view_state = ViewState::Details,
+            _ => {
+                self.view_state = ViewState::Partitions;
+                self.refresh_partitions();
+            }
+        }
+    }
+
+    // A cluster which can't be reached is reported in the status line,
+    // as when listing jobs, and only fails the view if it is the only one
+    fn refresh_partitions(&mut self) {
+        let (partitions, errors) = get_partitions_from(&self.visible_clusters());
+        self.partitions = match errors.is_empty() {
+            true => Ok(partitions),
+            false if partitions.is_empty() => Err(errors.join("; ")),
+            false => {
+                self.status = Some(Err(errors.join("; ")));
+                Ok(partitions)
+            }
+        };
+        self.partitions_refreshed = Instant::now();
+    }
+
+    pub fn next_reason(&mut self) {
+        if self.reasons.is_empty() {
+            return;
+        }
+        let i = match self.reason_state.selected() {
+            Some(i) if i < self.reasons.len() - 1 => i + 1,
+            _ => 0,
+        };
+        self.reason_state.select(Some(i));
+    }
+
+    pub fn previous_reason(&mut self) {
+        if self.reasons.is_empty() {
+            return;
+        }
+        let i = match self.reason_state.selected() {
+            Some(0) | None => self.reasons.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.reason_state.select(Some(i));
+    }
+
+    // In the pending reasons panel, restrict the jobs list to the
+    // pending jobs held back by the selected reason. In the templates
+    // panel, start submitting a job from the selected template.
+    pub fn select(&mut self) {
+        match self.view_state {
+            ViewState::Reasons => {
+                if let Some(i) = self.reason_state.selected() {
+                    self.reason_filter = Some(self.reasons[i].reason.clone());
+                    self.update_jobs();
+                    self.home();
+                }
+            }
+            ViewState::Templates => self.use_template(),
+            _ => (),
+        }
+    }
+
+    // Templates are read from disk whenever the panel is opened, so
+    // changes to them show up without a restart.
+    pub fn toggle_templates(&mut self) {
+        match self.view_state {
+            ViewState::Templates => self.view_state = ViewState::Details,
+            _ => {
+                self.templates = load_templates();
+                let selected = match &self.templates {
+                    Ok(templates) if !templates.is_empty() => Some(0),
+                    _ => None,
+                };
+                self.template_state.select(selected);
+                self.view_state = ViewState::Templates;
+            }
+        }
+    }
+
+    pub fn next_template(&mut self) {
+        let n = self.templates.as_ref().map_or(0, |t| t.len());
+        if n == 0 {
+            return;
+        }
+        let i = match self.template_state.selected() {
+            Some(i) if i < n - 1 => i + 1,
+            _ => 0,
+        };
+        self.template_state.select(Some(i));
+    }
+
+    pub fn previous_template(&mut self) {
+        let n = self.templates.as_ref().map_or(0, |t| t.len());
+        if n == 0 {
+            return;
+        }
+        let i = match self.template_state.selected() {
+            Some(0) | None => n - 1,
+            Some(i) => i - 1,
+        };
+        self.template_state.select(Some(i));
+    }
+
+    fn use_template(&mut self) {
+        let Some(i) = self.template_state.selected() else {
+            return;
+        };
+        let Some(template) = self.templates.as_ref().ok().and_then(|t| t.get(i)).cloned() else {
+            return;
+        };
+        let cluster = self.submit_cluster();
+        let placeholders = template.placeholders();
+        if placeholders.is_empty() {
+            self.submit_script(&cluster, &template.script, &[]);
+            return;
+        }
+        let title = match cluster.as_str() {
+            "" => format!("New job from {}", template.name),
+            _ => format!("New job on {} from {}", cluster, template.name),
+        };
+        let form = Form::new(title, placeholders);
+        self.form = Some((FormPurpose::SubmitTemplate { cluster, template }, form));
+        self.editor_state = EditorState::Form;
+    }
+
+    fn submit_script(&mut self, cluster: &str, script: &str, options: &[(String, String)]) {
+        match self.backend(cluster).submit(script, options) {
+            Ok(job_id) => {
+                self.status = Some(Ok(format!("Submitted job {}", job_id)));
+                self.jump_to = Some((cluster.to_string(), job_id));
+            }
+            Err(e) => self.status = Some(Err(e)),
+        }
+        self.reload_jobs();
+    }
+
+    pub fn selected_job(&self) -> Option<&Job> {
+        match self.rows.get(self.list_state.selected()?)? {
+            JobRow::Job(i) | JobRow::ArrayTask(i) => Some(&self.jobs[*i]),
+            JobRow::Array { .. } => None,
+        }
+    }
+
+    // The job (or whole job array) under the cursor, and its tasks.
+    pub fn selected_target(&self) -> Option<(String, Vec<&Job>)> {
+        match self.rows.get(self.list_state.selected()?)? {
+            JobRow::Job(i) | JobRow::ArrayTask(i) => {
+                Some((self.jobs[*i].JobID.clone(), vec![&self.jobs[*i]]))
+            }
+            JobRow::Array { id, tasks, .. } => {
+                Some((id.clone(), tasks.iter().map(|t| &self.jobs[*t]).collect()))
+            }
+        }
+    }
+
+    pub fn edit_job(&mut self) {
+        let Some((job_id, tasks)) = self.selected_target() else {
+            return;
+        };
+        let Some(job) = tasks.into_iter().find(|j| j.State == "PENDING") else {
+            self.status = Some(Err(format!(
+                "{} is not pending, only pending jobs can be edited",
+                job_id
+            )));
+            return;
+        };
+        // labels are the keys given to scontrol update
+        let fields = [
+            ("TimeLimit", &job.TimeLimit),
+            ("Partition", &job.Partition),
+            ("NumTasks", &job.NumTasks),
+            ("QOS", &job.QOS),
+            ("Name", &job.Name),
+            ("Nice", &job.Nice),
+            ("ReqNodeList", &job.ReqNodes),
+            ("ExcNodeList", &job.ExcNodes),
+        ]
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .to_vec();
+        let cluster = job.Cluster.clone();
+        let form = Form::new(format!("Edit job {}", job_id), fields);
+        self.form = Some((FormPurpose::UpdateJob { cluster, job_id }, form));
+        self.editor_state = EditorState::Form;
+    }
+
+    // Submit the selected job's batch script again, with its main
+    // options pre-filled so they can be tweaked first (e.g. a longer
+    // time limit after a timeout).
+    pub fn resubmit_job(&mut self) {
+        if !self.require_task("Resubmitting") {
+            return;
+        }
+        let Some(job) = self.selected_job() else {
+            return;
+        };
+        let script = match self.backend(&job.Cluster).get_batch_script(&job.JobID) {
+            Ok(script) => script,
+            Err(e) => {
+                self.status = Some(Err(e));
+                return;
+            }
+        };
+        // scontrol knows how the memory and CPUs were asked for, but
+        // may have forgotten the job, so that is only a nice to have
+        let info = self
+            .backend(&job.Cluster)
+            .show_job(&job.JobID)
+            .unwrap_or_default();
+        let fields = resubmit_fields(job, &info);
+        let cluster = job.Cluster.clone();
+        let form = Form::new(format!("Resubmit job {}", job.JobID), fields);
+        self.form = Some((FormPurpose::Submit { cluster, script }, form));
+        self.editor_state = EditorState::Form;
+    }
+
+    // Cancel the job (or whole array) under the cursor, once confirmed
+    pub fn cancel_job(&mut self) {
+        let Some((job_id, tasks)) = self.selected_target() else {
+            return;
+        };
+        let title = match tasks.len() {
+            1 => format!("Cancel job {}?", job_id),
+            n => format!("Cancel array {} ({} tasks)?", job_id, n),
+        };
+        let jobs = vec![(tasks[0].Cluster.clone(), job_id)];
+        self.form = Some((FormPurpose::Cancel { jobs }, Form::new(title, vec![])));
+        self.editor_state = EditorState::Form;
+    }
+
+    // Cancel all of your jobs in the list: filter the list down to the
+    // jobs to get rid of (e.g. by name) and cancel them in one go, once
+    // the number of jobs has been typed in. Other users' jobs are left
+    // alone, even for admins who could cancel them.
+    pub fn cancel_listed_jobs(&mut self) {
+        let filter = self.text_area.lines().concat();
+        if filter.is_empty() || filter_regex(&filter).is_err() {
+            self.status = Some(Err(
+                "Filter the list down to the jobs to cancel first".to_string()
+            ));
+            return;
+        }
+        let jobs = match self.own_listed_jobs() {
+            Ok(jobs) if jobs.is_empty() => {
+                self.status = Some(Err(format!("None of your jobs match {}", filter)));
+                return;
+            }
+            Ok(jobs) => jobs,
+            Err(e) => {
+                self.status = Some(Err(e));
+                return;
+            }
+        };
+        let title = format!("Cancel {} of your jobs matching {}?", jobs.len(), filter);
+        let confirm = format!("Type {} (or yes) to confirm", jobs.len());
+        let form = Form::new(title, vec![(confirm, String::new())]);
+        self.form = Some((FormPurpose::Cancel { jobs }, form));
+        self.editor_state = EditorState::Form;
+    }
+
+    // The listed jobs which haven't ended and belong to whoever
+    // lazyslurm runs as on their cluster
+    fn own_listed_jobs(&self) -> Result<Vec<(String, String)>, String> {
+        let mut users: HashMap<&str, String> = HashMap::new();
+        let mut jobs = vec![];
+        for job in self.jobs.iter().filter(|j| !is_finished(&j.State)) {
+            if !users.contains_key(job.Cluster.as_str()) {
+                let user = self.backend(&job.Cluster).current_user()?;
+                users.insert(&job.Cluster, user);
+            }
+            if users[job.Cluster.as_str()] == job.UserName {
+                jobs.push((job.Cluster.clone(), job.JobID.clone()));
+            }
+        }
+        Ok(jobs)
+    }
+
+    // Hold (or release) the job or array under the cursor, once confirmed
+    pub fn hold_job(&mut self, hold: bool) {
+        let Some((job_id, tasks)) = self.selected_target() else {
+            return;
+        };
+        let title = match (hold, tasks.len()) {
+            (true, 1) => format!("Hold job {}?", job_id),
+            (true, n) => format!("Hold the pending tasks of array {} ({} tasks)?", job_id, n),
+            (false, 1) => format!("Release job {}?", job_id),
+            (false, n) => format!("Release array {} ({} tasks)?", job_id, n),
+        };
+        let purpose = FormPurpose::Hold {
+            cluster: tasks[0].Cluster.clone(),
+            job_id,
+            hold,
+        };
+        self.form = Some((purpose, Form::new(title, vec![])));
+        self.editor_state = EditorState::Form;
+    }
+
+    pub fn submit_form(&mut self) {
+        let Some((purpose, form)) = self.form.take() else {
+            return;
+        };
+        self.editor_state = EditorState::Normal;
+        match purpose {
+            FormPurpose::UpdateJob { cluster, job_id } => {
+                let changes = form.changed();
+                if changes.is_empty() {
+                    self.status = Some(Ok(format!("Nothing to change for job {}", job_id)));
+                    return;
+                }
+                let summary: Vec<String> = changes
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                self.status = Some(
+                    self.backend(&cluster)
+                        .update_job(&job_id, &changes)
+                        .map(|_| format!("Updated job {}: {}", job_id, summary.join(" "))),
+                );
+                self.reload_jobs();
+            }
+            FormPurpose::Submit { cluster, script } => {
+                self.submit_script(&cluster, &script, &form.values());
+            }
+            FormPurpose::SubmitTemplate { cluster, template } => {
+                self.submit_script(&cluster, &template.fill(&form.values()), &[]);
+            }
+            FormPurpose::Cancel { jobs } => {
+                // a bulk cancel asks for the number of jobs to be typed in
+                if let Some((_, typed)) = form.values().first() {
+                    if *typed != jobs.len().to_string() && !typed.eq_ignore_ascii_case("yes") {
+                        self.status = Some(Err(format!(
+                            "Nothing cancelled: type {} (or yes) to confirm",
+                            jobs.len()
+                        )));
+                        return;
+                    }
+                }
+                let errors: Vec<String> = jobs
+                    .iter()
+                    .filter_map(|(cluster, job_id)| self.backend(cluster).cancel_job(job_id).err())
+                    .collect();
+                self.status = Some(match (&jobs[..], errors.first()) {
+                    ([(_, job_id)], None) => Ok(format!("Cancelled job {}", job_id)),
+                    (_, None) => Ok(format!("Cancelled {} jobs", jobs.len())),
+                    ([_], Some(e)) => Err(e.clone()),
+                    (_, Some(e)) => Err(format!(
+                        "{} of {} jobs could not be cancelled: {}",
+                        errors.len(),
+                        jobs.len(),
+                        e
+                    )),
+                });
+                self.reload_jobs();
+            }
+            FormPurpose::Hold {
+                cluster,
+                job_id,
+                hold,
+            } => {
+                let backend = self.backend(&cluster);
+                self.status = Some(match hold {
+                    true => backend
+                        .hold_job(&job_id)
+                        .map(|_| format!("Held job {}", job_id)),
+                    false => backend
+                        .release_job(&job_id)
+                        .map(|_| format!("Released job {}", job_id)),
+                });
+                self.reload_jobs();
+            }
+        }
+    }
+
+    // The views of a single job need a task rather than a whole array,
+    // so say how to get one when the cursor is on an array.
+    fn require_task(&mut self, what: &str) -> bool {
+        let row = self.list_state.selected().and_then(|i| self.rows.get(i));
+        if let Some(JobRow::Array { id, .. }) = row {
+            self.status = Some(Err(format!(
+                "{} needs a single task: expand array {} with <space> and select one",
+                what, id
+            )));
+            return false;
+        }
+        true
+    }
+
+    // Remember which job the side panel is about, so that it stays
+    // put while the cursor moves.
+    fn inspect_selected_job(&mut self, what: &str) -> bool {
+        if !self.require_task(what) {
+            return false;
+        }
+        let Some(job) = self.selected_job() else {
+            return false;
+        };
+        (self.inspected_job_id, self.inspected_cluster) = (job.JobID.clone(), job.Cluster.clone());
+        true
+    }
+
+    pub fn toggle_steps(&mut self) {
+        if let ViewState::Steps = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("Steps") {
+            return;
+        }
+        self.view_state = ViewState::Steps;
+        self.refresh_steps();
+    }
+
+    fn refresh_steps(&mut self) {
+        // jobs which have left the queue have to be looked up in sacct
+        let live = self
+            .inspected_job()
+            .is_some_and(|j| matches!(j.State.as_str(), "RUNNING" | "COMPLETING" | "SUSPENDED"));
+        self.steps = self
+            .backend(&self.inspected_cluster)
+            .get_steps(&self.inspected_job_id, live);
+        self.steps_refreshed = Instant::now();
+    }
+
+    pub fn toggle_usage(&mut self) {
+        if let ViewState::Usage = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("Usage") {
+            return;
+        }
+        self.view_state = ViewState::Usage;
+        self.refresh_usage();
+    }
+
+    pub fn toggle_dependencies(&mut self) {
+        if let ViewState::Dependencies = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("Dependencies") {
+            return;
+        }
+        self.view_state = ViewState::Dependencies;
+    }
+
+    // Everything scontrol knows about the job. This is too slow to
+    // fetch for every job in the list, so it is only done on request.
+    pub fn toggle_info(&mut self) {
+        if let ViewState::Info = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("Info") {
+            return;
+        }
+        self.job_info = self
+            .backend(&self.inspected_cluster)
+            .show_job(&self.inspected_job_id);
+        self.panel_scroll = 0;
+        self.view_state = ViewState::Info;
+    }
+
+    pub fn toggle_script(&mut self) {
+        if let ViewState::Script = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("The batch script") {
+            return;
+        }
+        self.batch_script = self
+            .backend(&self.inspected_cluster)
+            .get_batch_script(&self.inspected_job_id);
+        self.panel_scroll = 0;
+        self.view_state = ViewState::Script;
+    }
+
+    // Follow the end of the selected job's output (or errors, when they
+    // go to a separate file), as written on the cluster.
+    pub fn toggle_log(&mut self, stderr: bool) {
+        if let ViewState::Log = self.view_state {
+            self.view_state = ViewState::Details;
+            return;
+        }
+        if !self.inspect_selected_job("The output") {
+            return;
+        }
+        let Some(job) = self.inspected_job() else {
+            return;
+        };
+        self.log_path = match stderr {
+            true => job.STDERR.clone(),
+            false => job.STDOUT.clone(),
+        };
+        self.panel_scroll = 0;
+        self.view_state = ViewState::Log;
+        self.refresh_log();
+    }
+
+    fn refresh_log(&mut self) {
+        self.log = self
+            .backend(&self.inspected_cluster)
+            .get_log(&self.inspected_job_id, &self.log_path);
+        self.log_refreshed = Instant::now();
+    }
+
+    // Save what is on screen (the overview table, or otherwise the
+    // filtered jobs) to a timestamped file in the working directory.
+    pub fn export(&mut self, format: Format) {
+        let (what, contents) = match self.view_state {
+            ViewState::Overview => ("overview", Ok(format_overview(&self.overview, format))),
+            _ => ("jobs", format_jobs(&self.jobs, &[], format)),
+        };
+        let extension = match format {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+            Format::Table => "txt",
+        };
+        let file_name = format!(
+            "lazyslurm-{}-{}.{}",
+            what,
+            Local::now().format("%Y%m%d-%H%M%S"),
+            extension
+        );
+        let path = env::current_dir()
+            .map(|dir| dir.join(&file_name))
+            .unwrap_or(file_name.into());
+        self.status = Some(
+            contents
+                .and_then(|contents| {
+                    fs::write(&path, contents)
+                        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+                })
+                .map(|_| format!("Exported {} to {}", what, path.display())),
+        );
+    }
+
+    pub fn scroll_panel(&mut self, lines: i32) {
+        let max_scroll = match (&self.view_state, &self.job_info, &self.batch_script) {
+            (ViewState::Info, Ok(info), _) => info.len().saturating_sub(1) as i32,
+            (ViewState::Script, _, Ok(script)) => script.lines().count().saturating_sub(1) as i32,
+            (ViewState::Log, _, _) => match &self.log {
+                Ok(log) => log.lines().count().saturating_sub(1) as i32,
+                Err(_) => 0,
+            },
+            (ViewState::Help, _, _) => crate::ui::HELP.lines().count().saturating_sub(1) as i32,
+            _ => 0,
+        };
+        // the log is scrolled from the end, so that new output shows up
+        let lines = match self.view_state {
+            ViewState::Log => -lines,
+            _ => lines,
+        };
+        self.panel_scroll = (self.panel_scroll as i32 + lines).clamp(0, max_scroll) as u16;
+    }
+
+    fn refresh_usage(&mut self) {
+        self.usage = self
+            .backend(&self.inspected_cluster)
+            .get_usage(&self.inspected_job_id);
+        self.usage_refreshed = Instant::now();
+    }
+
+    fn load_efficiency(&mut self) {
+        let Some(job) = self.selected_job() else {
+            return;
+        };
+        let key = (job.Cluster.clone(), job.JobID.clone());
+        if is_finished(&job.State) && !self.efficiency.contains_key(&key) {
+            let efficiency = self.backend(&key.0).get_job_efficiency(&key.1);
+            self.efficiency.insert(key, efficiency);
+        }
+    }
+
+    pub fn inspected_job(&self) -> Option<&Job> {
+        self.all_jobs
+            .iter()
+            .find(|j| j.JobID == self.inspected_job_id && j.Cluster == self.inspected_cluster)
+    }
+
+    // Expand or collapse the array under the cursor. If the cursor is
+    // on one of its tasks, the array is collapsed back into one row.
+    pub fn toggle_array(&mut self) {
+        let Some(i) = self.list_state.selected() else {
+            return;
+        };
+        let task = match &self.rows[i] {
+            JobRow::Array { tasks, .. } => tasks[0],
+            JobRow::ArrayTask(j) => *j,
+            JobRow::Job(_) => return,
+        };
+        let key = (
+            self.jobs[task].Cluster.clone(),
+            self.jobs[task].ArrayJobID.clone(),
+        );
+        if !self.expanded_arrays.remove(&key) {
+            self.expanded_arrays.insert(key.clone());
+        }
+        self.rows = build_rows(&self.jobs, &self.expanded_arrays);
+        let parent = self.rows.iter().position(|r| match r {
+            JobRow::Array { id, tasks, .. } => *id == key.1 && self.jobs[tasks[0]].Cluster == key.0,
+            _ => false,
+        });
+        self.list_state.select(parent);
+    }
+
+    pub fn next(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
 
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.jobs.len() - 1 {
+                if i >= self.rows.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -131,7 +1336,7 @@ impl App<'_> {
     }
     pub fn previous(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
@@ -139,7 +1344,7 @@ impl App<'_> {
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.jobs.len() - 1
+                    self.rows.len() - 1
                 } else {
                     i - 1
                 }
@@ -149,17 +1354,17 @@ impl App<'_> {
         self.list_state.select(Some(i));
     }
 
-    pub fn down_5(&mut self) -> () {
+    pub fn down_5(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
 
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.jobs.len() - 5 {
-                    self.jobs.len() - 1
+                if i >= self.rows.len().saturating_sub(5) {
+                    self.rows.len() - 1
                 } else {
                     i + 5
                 }
@@ -168,58 +1373,96 @@ impl App<'_> {
         };
         self.list_state.select(Some(i));
     }
-    pub fn up_5(&mut self) -> () {
+    pub fn up_5(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
 
         let i = match self.list_state.selected() {
-            Some(i) => {
-                if i <= 5 {
-                    0
-                } else {
-                    i - 5
-                }
-            }
+            Some(i) => i.saturating_sub(5),
             None => 0,
         };
         self.list_state.select(Some(i));
     }
-    pub fn home(&mut self) -> () {
+    pub fn home(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
         self.list_state.select(Some(0));
     }
-    pub fn end(&mut self) -> () {
+    pub fn end(&mut self) {
         self.view_state = ViewState::Details;
-        if self.jobs.is_empty() {
+        if self.rows.is_empty() {
             self.list_state.select(None);
             return;
         }
-        self.list_state.select(Some(self.jobs.len() - 1));
+        self.list_state.select(Some(self.rows.len() - 1));
     }
-    pub fn toggle_help(&mut self) -> () {
+    pub fn toggle_help(&mut self) {
         match self.view_state {
             ViewState::Help => self.view_state = ViewState::Details,
             _ => self.view_state = ViewState::Help,
         }
+        self.panel_scroll = 0;
     }
-    pub fn reset_view(&mut self) -> () {
+    pub fn reset_view(&mut self) {
+        // closing a form leaves everything else as it was
+        if self.form.take().is_some() {
+            self.editor_state = EditorState::Normal;
+            return;
+        }
         self.view_state = ViewState::Details;
         self.editor_state = EditorState::Normal;
+        self.status = None;
+        self.jump_to = None;
+        if self.reason_filter.take().is_some() {
+            self.update_jobs();
+        }
     }
-    pub fn toggle_focus(&mut self) -> () {
+    pub fn toggle_focus(&mut self) {
         match self.editor_state {
             EditorState::Normal => self.editor_state = EditorState::Editing,
             EditorState::Editing => self.editor_state = EditorState::Normal,
+            EditorState::Form => (),
         }
     }
-    pub fn text_input(&mut self, key: KeyEvent) -> () {
+    pub fn text_input(&mut self, key: KeyEvent) {
         self.text_area.input(key);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::squeue_json::parse_jobs;
+
+    #[test]
+    fn output_patterns() {
+        let jobs = parse_jobs(include_str!("../tests/fixtures/squeue-23.02.json")).unwrap();
+        let job = jobs.iter().find(|j| j.JobID == "1001").unwrap();
+        let task = jobs.iter().find(|j| j.JobID == "1010_1").unwrap();
+
+        assert_eq!(
+            output_pattern("/home/a/slurm-1001.out", job),
+            "/home/a/slurm-%j.out"
+        );
+        // the directory is left alone, even when it has the ID in it
+        assert_eq!(
+            output_pattern("/scratch/run1001x/slurm-1001.out", job),
+            "/scratch/run1001x/slurm-%j.out"
+        );
+        assert_eq!(output_pattern("1001-1001.log", job), "1001-%j.log");
+        assert_eq!(
+            output_pattern("/home/a/train.out", job),
+            "/home/a/train.out"
+        );
+        assert_eq!(
+            output_pattern("/scratch/1010_1/slurm-1010_1.out", task),
+            "/scratch/1010_1/slurm-%A_%a.out"
+        );
+    }
+}
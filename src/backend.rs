@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::command::Runner;
+use crate::config::{Config, SimulatorConfig};
+use crate::jobs::{get_jobs, squeue_supports_json, Job};
+use crate::logs::get_log;
+use crate::partitions::{get_partitions, Partition};
+use crate::recording::{load_replay, Recorder, Recording};
+use crate::sbatch::submit;
+use crate::scancel::{cancel_job, get_user};
+use crate::scontrol::{get_batch_script, hold_job, release_job, show_job, update_job};
+use crate::simulator::Simulator;
+use crate::slurmrestd::SlurmRestd;
+use crate::steps::{get_steps, Step};
+use crate::usage::{get_job_efficiency, get_usage, get_user_efficiency, Efficiency, StepUsage};
+
+// Everything the app asks of Slurm. By default this is done by running
+// the usual commands (squeue, sacct, scontrol, ...), but it can also be
+// done over slurmrestd's HTTP API. Errors are strings to show the user.
+// Slow requests are made from a background thread, hence Send + Sync.
+pub trait Backend: Send + Sync {
+    // with `finished`, also the jobs which have ended but which Slurm
+    // still remembers (for MinJobAge, 5 minutes by default)
+    fn get_jobs(&self, finished: bool) -> Result<Vec<Job>, String>;
+    fn get_steps(&self, job_id: &str, live: bool) -> Result<Vec<Step>, String>;
+    fn get_usage(&self, job_id: &str) -> Result<Vec<StepUsage>, String>;
+    fn get_job_efficiency(&self, job_id: &str) -> Result<Efficiency, String>;
+    fn get_user_efficiency(&self) -> Result<HashMap<String, Efficiency>, String>;
+    fn show_job(&self, job_id: &str) -> Result<Vec<(String, String)>, String>;
+    fn get_batch_script(&self, job_id: &str) -> Result<String, String>;
+    // changes are scontrol update keys and values, e.g. TimeLimit=2:00:00
+    fn update_job(&self, job_id: &str, changes: &[(String, String)]) -> Result<(), String>;
+    // options are sbatch long options and values, e.g. --time=2:00:00
+    fn submit(&self, script: &str, options: &[(String, String)]) -> Result<String, String>;
+    // who the jobs are cancelled, held etc. as
+    fn current_user(&self) -> Result<String, String>;
+    // these take a job's ID, or an array's to act on all of its tasks
+    fn cancel_job(&self, job_id: &str) -> Result<(), String>;
+    fn hold_job(&self, job_id: &str) -> Result<(), String>;
+    fn release_job(&self, job_id: &str) -> Result<(), String>;
+    // how busy the nodes of each partition are
+    fn get_partitions(&self) -> Result<Vec<Partition>, String>;
+    // the last lines of a job's output, `path` being its STDOUT or STDERR
+    fn get_log(&self, job_id: &str, path: &str) -> Result<String, String>;
+}
+
+// The Slurm command line tools, run locally or over SSH
+pub struct Cli {
+    runner: Runner,
+    squeue_json: AtomicBool,
+}
+
+impl Cli {
+    pub fn new(runner: Runner) -> Self {
+        let squeue_json = AtomicBool::new(squeue_supports_json(&runner));
+        Cli {
+            runner,
+            squeue_json,
+        }
+    }
+}
+
+impl Backend for Cli {
+    fn get_jobs(&self, finished: bool) -> Result<Vec<Job>, String> {
+        get_jobs(&self.runner, &self.squeue_json, finished)
+    }
+
+    fn get_steps(&self, job_id: &str, live: bool) -> Result<Vec<Step>, String> {
+        get_steps(&self.runner, job_id, live)
+    }
+
+    fn get_usage(&self, job_id: &str) -> Result<Vec<StepUsage>, String> {
+        get_usage(&self.runner, job_id)
+    }
+
+    fn get_job_efficiency(&self, job_id: &str) -> Result<Efficiency, String> {
+        get_job_efficiency(&self.runner, job_id)
+    }
+
+    fn get_user_efficiency(&self) -> Result<HashMap<String, Efficiency>, String> {
+        get_user_efficiency(&self.runner)
+    }
+
+    fn show_job(&self, job_id: &str) -> Result<Vec<(String, String)>, String> {
+        show_job(&self.runner, job_id)
+    }
+
+    fn get_batch_script(&self, job_id: &str) -> Result<String, String> {
+        get_batch_script(&self.runner, job_id)
+    }
+
+    fn update_job(&self, job_id: &str, changes: &[(String, String)]) -> Result<(), String> {
+        update_job(&self.runner, job_id, changes)
+    }
+
+    fn submit(&self, script: &str, options: &[(String, String)]) -> Result<String, String> {
+        submit(&self.runner, script, options)
+    }
+
+    fn current_user(&self) -> Result<String, String> {
+        get_user(&self.runner)
+    }
+
+    fn cancel_job(&self, job_id: &str) -> Result<(), String> {
+        cancel_job(&self.runner, job_id)
+    }
+
+    fn hold_job(&self, job_id: &str) -> Result<(), String> {
+        hold_job(&self.runner, job_id)
+    }
+
+    fn release_job(&self, job_id: &str) -> Result<(), String> {
+        release_job(&self.runner, job_id)
+    }
+
+    fn get_partitions(&self) -> Result<Vec<Partition>, String> {
+        get_partitions(&self.runner)
+    }
+
+    fn get_log(&self, _job_id: &str, path: &str) -> Result<String, String> {
+        get_log(&self.runner, path)
+    }
+}
+
+// A cluster and how to reach it. The name is what the UI calls it, and
+// is empty when only the one cluster is set up.
+#[derive(Clone)]
+pub struct Cluster {
+    pub name: String,
+    pub backend: Arc<dyn Backend>,
+}
+
+// With `record`, every command run is also saved to that directory.
+pub fn from_config(config: &Config, record: Option<&Path>) -> Result<Vec<Cluster>, String> {
+    let recorder = record.map(Recorder::create).transpose()?;
+    let recording = |cluster: &str| {
+        recorder
+            .as_ref()
+            .map(|r| Arc::new(Recording::Record(r.for_cluster(cluster))))
+    };
+    if !config.clusters.is_empty() {
+        if config.host.is_some() || config.slurmrestd.is_some() {
+            return Err("use either clusters or host/slurmrestd, not both".to_string());
+        }
+        return config
+            .clusters
+            .iter()
+            .map(|c| {
+                let runner = Runner {
+                    host: c.host.clone(),
+                    cluster: c.cluster.clone(),
+                    recording: recording(&c.name),
+                };
+                runner.connect()?;
+                let backend: Arc<dyn Backend> = Arc::new(Cli::new(runner));
+                Ok(Cluster {
+                    name: c.name.clone(),
+                    backend,
+                })
+            })
+            .collect();
+    }
+    let backend: Arc<dyn Backend> = match (&config.slurmrestd, &config.host) {
+        (Some(_), Some(_)) => return Err("use either slurmrestd or a host, not both".to_string()),
+        (Some(_), None) if recorder.is_some() => {
+            return Err("only the command line tools can be recorded, not slurmrestd".to_string())
+        }
+        (Some(rest), None) => Arc::new(SlurmRestd::new(rest)?),
+        (None, host) => {
+            let runner = Runner {
+                host: host.clone(),
+                cluster: None,
+                recording: recording(""),
+            };
+            runner.connect()?;
+            Arc::new(Cli::new(runner))
+        }
+    };
+    Ok(vec![Cluster {
+        name: String::new(),
+        backend,
+    }])
+}
+
+// The clusters in a recording made with --record, played back
+pub fn from_recording(dir: &Path) -> Result<Vec<Cluster>, String> {
+    let clusters = load_replay(dir)?
+        .into_iter()
+        .map(|(name, replay)| {
+            let runner = Runner {
+                recording: Some(Arc::new(Recording::Replay(replay))),
+                ..Runner::default()
+            };
+            let backend: Arc<dyn Backend> = Arc::new(Cli::new(runner));
+            Cluster { name, backend }
+        })
+        .collect();
+    Ok(clusters)
+}
+
+// A made up cluster, for demos and development
+pub fn simulated(config: &SimulatorConfig) -> Vec<Cluster> {
+    vec![Cluster {
+        name: String::new(),
+        backend: Arc::new(Simulator::new(config)),
+    }]
+}
+
+// Jobs from each of the clusters, labelled with the cluster's name,
+// and what went wrong with any which could not be reached.
+pub fn get_jobs_from(clusters: &[&Cluster], finished: bool) -> (Vec<Job>, Vec<String>) {
+    let mut jobs = vec![];
+    let mut errors = vec![];
+    for cluster in clusters {
+        match cluster.backend.get_jobs(finished) {
+            Ok(cluster_jobs) if cluster.name.is_empty() => jobs.extend(cluster_jobs),
+            Ok(cluster_jobs) => jobs.extend(cluster_jobs.into_iter().map(|job| Job {
+                Cluster: cluster.name.clone(),
+                ..job
+            })),
+            Err(e) if cluster.name.is_empty() => errors.push(e),
+            Err(e) => errors.push(format!("{}: {}", cluster.name, e)),
+        }
+    }
+    (jobs, errors)
+}
+
+// Partitions of each of the clusters, like get_jobs_from
+pub fn get_partitions_from(clusters: &[&Cluster]) -> (Vec<Partition>, Vec<String>) {
+    let mut partitions = vec![];
+    let mut errors = vec![];
+    for cluster in clusters {
+        match cluster.backend.get_partitions() {
+            Ok(p) => partitions.extend(p.into_iter().map(|p| Partition {
+                cluster: cluster.name.clone(),
+                ..p
+            })),
+            Err(e) if cluster.name.is_empty() => errors.push(e),
+            Err(e) => errors.push(format!("{}: {}", cluster.name, e)),
+        }
+    }
+    (partitions, errors)
+}
@@ -0,0 +1,304 @@
+use regex::Regex;
+use std::path::PathBuf;
+
+use crate::jobs::{filter_regex, GroupBy};
+
+// Command line arguments. With none, lazyslurm starts the TUI.
+pub const USAGE: &str = "\
+Usage: lazyslurm [options]
+       lazyslurm --print [--format FORMAT] [--fields F1,F2,...] [regex]
+       lazyslurm overview [--by GROUP] [--format FORMAT] [regex]
+
+Commands:
+  overview           print the number of running/pending jobs and GPUs in
+                     use per group, counting the jobs matching regex (or
+                     all jobs), and exit
+
+Options:
+  --print            print the jobs matching regex (or all jobs) and exit
+  --format FORMAT    output format: table (or text), markdown, json or csv
+                     (default: table)
+  --fields FIELDS    comma separated job fields to print (default: all),
+                     e.g. JobID,Name,State,STDOUT
+  --by GROUP         what to group the overview by: user, partition,
+                     account, qos, node, gpu or cluster (default: user)
+  --host HOST        run squeue etc. on HOST over SSH, e.g. a login node
+  --slurmrestd URL   get jobs from slurmrestd at URL instead of squeue etc.
+                     (the token comes from SLURM_JWT or the config)
+  --record DIR       save every Slurm command run and its output to DIR
+  --replay DIR       play back a recording made with --record instead of
+                     running Slurm commands
+  --simulate         show a made up cluster instead of a real one (see
+                     [simulator] in the config file)
+  -h, --help         show this message
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Table,
+    Markdown,
+}
+
+impl Format {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "table" | "text" => Ok(Format::Table),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(format!(
+                "unknown format {}, expected table, markdown, json or csv",
+                format
+            )),
+        }
+    }
+}
+
+fn parse_group_by(group_by: &str) -> Result<GroupBy, String> {
+    match group_by {
+        "user" => Ok(GroupBy::User),
+        "partition" => Ok(GroupBy::Partition),
+        "account" => Ok(GroupBy::Account),
+        "qos" => Ok(GroupBy::Qos),
+        "node" => Ok(GroupBy::Node),
+        "gpu" => Ok(GroupBy::GpuType),
+        "cluster" => Ok(GroupBy::Cluster),
+        _ => Err(format!(
+            "unknown grouping {}, expected user, partition, account, qos, node, gpu or cluster",
+            group_by
+        )),
+    }
+}
+
+pub enum Command {
+    Tui,
+    Help,
+    Print {
+        format: Format,
+        fields: Vec<String>,
+        filter: Regex,
+    },
+    Overview {
+        format: Format,
+        group_by: GroupBy,
+        filter: Regex,
+    },
+}
+
+pub struct Args {
+    pub command: Command,
+    pub host: Option<String>,
+    pub slurmrestd: Option<String>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub simulate: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let command = Command::Tui;
+    let mut host = None;
+    let mut slurmrestd = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut simulate = false;
+    let mut print = false;
+    let mut overview = false;
+    let mut format = Format::Table;
+    let mut fields = vec![];
+    let mut group_by = None;
+    let mut filter = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Ok(Args {
+                    command: Command::Help,
+                    host,
+                    slurmrestd,
+                    record,
+                    replay,
+                    simulate,
+                })
+            }
+            "--print" => print = true,
+            "--format" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                format = Format::parse(&value)?;
+            }
+            "--fields" => {
+                let value = args.next().ok_or("--fields needs a value")?;
+                fields = value.split(',').map(|f| f.trim().to_string()).collect();
+            }
+            "--host" => {
+                host = Some(args.next().ok_or("--host needs a host name")?);
+            }
+            "--slurmrestd" => {
+                slurmrestd = Some(args.next().ok_or("--slurmrestd needs a URL")?);
+            }
+            "--record" => {
+                record = Some(args.next().ok_or("--record needs a directory")?.into());
+            }
+            "--replay" => {
+                replay = Some(args.next().ok_or("--replay needs a directory")?.into());
+            }
+            "--simulate" => simulate = true,
+            "--by" => {
+                let value = args.next().ok_or("--by needs a value")?;
+                group_by = Some(parse_group_by(&value)?);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            // the subcommand is the first argument which is not an option
+            "overview" if !print && !overview && filter.is_none() => overview = true,
+            _ if filter.is_none() => filter = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let remote = host.is_some() || slurmrestd.is_some();
+    if replay.is_some() && (record.is_some() || remote || simulate) {
+        return Err(
+            "--replay cannot be used with --record, --host, --slurmrestd or --simulate".to_string(),
+        );
+    }
+    if simulate && (record.is_some() || remote) {
+        return Err("--simulate cannot be used with --record, --host or --slurmrestd".to_string());
+    }
+
+    if overview {
+        if print || !fields.is_empty() {
+            return Err("--print and --fields cannot be used with overview".to_string());
+        }
+        let command = Command::Overview {
+            format,
+            group_by: group_by.unwrap_or_default(),
+            filter: filter_regex(&filter.unwrap_or_default())?,
+        };
+        return Ok(Args {
+            command,
+            host,
+            slurmrestd,
+            record,
+            replay,
+            simulate,
+        });
+    }
+    if group_by.is_some() {
+        return Err("--by can only be used with overview".to_string());
+    }
+    if !print {
+        if filter.is_some() {
+            return Err("a regex can only be given with --print".to_string());
+        }
+        return Ok(Args {
+            command,
+            host,
+            slurmrestd,
+            record,
+            replay,
+            simulate,
+        });
+    }
+    let command = Command::Print {
+        format,
+        fields,
+        filter: filter_regex(&filter.unwrap_or_default())?,
+    };
+    Ok(Args {
+        command,
+        host,
+        slurmrestd,
+        record,
+        replay,
+        simulate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn tui() {
+        let args = parse(&["--host", "login", "--record", "rec"]).unwrap();
+        assert!(matches!(args.command, Command::Tui));
+        assert_eq!(args.host.as_deref(), Some("login"));
+        assert_eq!(args.record, Some(PathBuf::from("rec")));
+        assert!(matches!(parse(&["-h"]).unwrap().command, Command::Help));
+    }
+
+    #[test]
+    fn print() {
+        let args = parse(&[
+            "--print",
+            "--format",
+            "csv",
+            "--fields",
+            "JobID, Name",
+            "Al",
+        ]);
+        let Command::Print {
+            format,
+            fields,
+            filter,
+        } = args.unwrap().command
+        else {
+            panic!("expected --print");
+        };
+        assert_eq!(format, Format::Csv);
+        assert_eq!(fields, ["JobID", "Name"]);
+        // case insensitive, as in the TUI
+        assert!(filter.is_match("alice"));
+
+        let Command::Print { filter, .. } = parse(&["--print"]).unwrap().command else {
+            panic!("expected --print");
+        };
+        assert!(filter.is_match(""));
+    }
+
+    #[test]
+    fn overview() {
+        let args = parse(&["overview", "--by", "gpu", "--format", "md", "gpu"]);
+        let Command::Overview {
+            format, group_by, ..
+        } = args.unwrap().command
+        else {
+            panic!("expected overview");
+        };
+        assert_eq!(format, Format::Markdown);
+        assert_eq!(group_by, GroupBy::GpuType);
+
+        // "overview" is only the subcommand when it comes first
+        let args = parse(&["--print", "overview"]).unwrap();
+        assert!(matches!(args.command, Command::Print { .. }));
+    }
+
+    #[test]
+    fn invalid() {
+        for args in [
+            &["--format", "xml"][..],
+            &["--format"],
+            &["--by", "colour", "overview"],
+            &["--frobnicate"],
+            &["--print", "a", "b"],
+            &["--print", "train("],
+            &["overview", "["],
+            // conflicting flags
+            &["--replay", "rec", "--record", "rec"],
+            &["--replay", "rec", "--host", "login"],
+            &["--simulate", "--slurmrestd", "http://localhost"],
+            &["overview", "--print"],
+            &["overview", "--fields", "JobID"],
+            &["--print", "--by", "user"],
+            &["alice"],
+        ] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+}
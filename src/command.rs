@@ -0,0 +1,252 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+
+use crate::recording::Recording;
+
+// Where Slurm commands are run: on this machine, or on a cluster's
+// login node over SSH (e.g. `--host login.cluster.ac.uk`), and which of
+// the clusters managed from there they are about.
+#[derive(Clone, Default)]
+pub struct Runner {
+    pub host: Option<String>,
+    // passed as --clusters to the commands which take it
+    pub cluster: Option<String>,
+    // commands are saved to, or played back from, a recording
+    pub recording: Option<Arc<Recording>>,
+}
+
+const MULTI_CLUSTER_COMMANDS: [&str; 6] =
+    ["squeue", "sacct", "scontrol", "sbatch", "sinfo", "scancel"];
+
+// Every command over SSH shares one connection, which stays open for a
+// while after lazyslurm exits, so each tick doesn't pay for a new login.
+fn ssh_options() -> Vec<String> {
+    let control_path = std::env::temp_dir().join("lazyslurm-ssh-%C");
+    vec![
+        "-o".to_string(),
+        "ControlMaster=auto".to_string(),
+        "-o".to_string(),
+        format!("ControlPath={}", control_path.display()),
+        "-o".to_string(),
+        "ControlPersist=10m".to_string(),
+    ]
+}
+
+// ssh runs the command through the remote shell, so arguments have to
+// be quoted to arrive unchanged
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+impl Runner {
+    fn command(&self, program: &str, args: &[&str]) -> Command {
+        let mut args: Vec<&str> = args.to_vec();
+        if let Some(cluster) = &self.cluster {
+            if MULTI_CLUSTER_COMMANDS.contains(&program) {
+                args.splice(0..0, ["--clusters", cluster.as_str()]);
+            }
+        }
+        match &self.host {
+            None => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+            Some(host) => {
+                let remote: Vec<String> = std::iter::once(program)
+                    .chain(args)
+                    .map(shell_quote)
+                    .collect();
+                let mut command = Command::new("ssh");
+                command
+                    .args(ssh_options())
+                    // never prompt for anything once the TUI is running
+                    .args(["-o", "BatchMode=yes", host, "--", &remote.join(" ")]);
+                command
+            }
+        }
+    }
+
+    // Run a Slurm command and return what it printed, or what went wrong
+    // in a form that can be shown to the user.
+    pub fn run(&self, program: &str, args: &[&str]) -> Result<String, String> {
+        self.recorded(program, args, || {
+            let output = self
+                .command(program, args)
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("failed to execute {}: {}", program, e))?;
+            check_output(program, output)
+        })
+    }
+
+    // As run, but with `input` written to the command's stdin
+    // (e.g. a batch script for sbatch).
+    pub fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<String, String> {
+        self.recorded(program, args, || {
+            let mut child = self
+                .command(program, args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("failed to execute {}: {}", program, e))?;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(input.as_bytes())
+                .map_err(|e| format!("failed to write to {}: {}", program, e))?;
+            let output = child
+                .wait_with_output()
+                .map_err(|e| format!("failed to execute {}: {}", program, e))?;
+            check_output(program, output)
+        })
+    }
+
+    // Commands are recorded as lazyslurm asked for them, before
+    // --clusters or ssh are added, so that a replay needs neither.
+    fn recorded(
+        &self,
+        program: &str,
+        args: &[&str],
+        run: impl FnOnce() -> Result<String, String>,
+    ) -> Result<String, String> {
+        match self.recording.as_deref() {
+            None => run(),
+            Some(Recording::Replay(replay)) => replay.run(program, args),
+            Some(Recording::Record(recorder)) => {
+                let result = run();
+                recorder.record(program, args, &result);
+                result
+            }
+        }
+    }
+
+    // Open the shared SSH connection before the TUI starts, so that any
+    // password or second factor can be typed in as usual.
+    pub fn connect(&self) -> Result<(), String> {
+        let Some(host) = &self.host else {
+            return Ok(());
+        };
+        let status = Command::new("ssh")
+            .args(ssh_options())
+            .args([host, "true"])
+            .status()
+            .map_err(|e| format!("failed to execute ssh: {}", e))?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(format!("could not connect to {}", host)),
+        }
+    }
+}
+
+fn check_output(program: &str, output: Output) -> Result<String, String> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(shell_quote("Name=my job"), "'Name=my job'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn local_commands() {
+        let runner = Runner {
+            cluster: Some("beta".to_string()),
+            ..Runner::default()
+        };
+        let command = runner.command("squeue", &["--me"]);
+        assert_eq!(command.get_program(), "squeue");
+        assert_eq!(args(&command), ["--clusters", "beta", "--me"]);
+        // only Slurm commands know about clusters
+        assert_eq!(args(&runner.command("tail", &["-n", "5"])), ["-n", "5"]);
+    }
+
+    #[test]
+    fn ssh_commands() {
+        let runner = Runner {
+            host: Some("login".to_string()),
+            cluster: Some("beta".to_string()),
+            ..Runner::default()
+        };
+        let command = runner.command("scontrol", &["update", "JobId=1", "Name=it's mine"]);
+        assert_eq!(command.get_program(), "ssh");
+        let args = args(&command);
+        assert_eq!(&args[..6], &ssh_options()[..]);
+        assert_eq!(
+            &args[6..],
+            [
+                "-o",
+                "BatchMode=yes",
+                "login",
+                "--",
+                r"'scontrol' '--clusters' 'beta' 'update' 'JobId=1' 'Name=it'\''s mine'"
+            ]
+        );
+    }
+
+    fn write_script(path: &Path, script: &str) {
+        fs::write(path, script).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    // With a stand-in for ssh which hands the command to a shell, as
+    // sshd would, and a stand-in for squeue which prints its arguments,
+    // they should arrive as they were given.
+    #[test]
+    fn through_a_shell() {
+        let dir = std::env::temp_dir().join(format!("lazyslurm-ssh-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write_script(
+            &dir.join("ssh"),
+            "#!/bin/sh\nwhile [ \"$1\" != -- ]; do shift; done\nexec sh -c \"$2\"\n",
+        );
+        write_script(&dir.join("squeue"), "#!/bin/sh\nprintf '%s\\n' \"$@\"\n");
+        let path = format!("{}:{}", dir.display(), std::env::var("PATH").unwrap());
+
+        let runner = Runner {
+            host: Some("login".to_string()),
+            cluster: Some("beta".to_string()),
+            ..Runner::default()
+        };
+        let given = ["--name", "my job", "it's", "$HOME", "a;b", "*"];
+        let output = runner
+            .command("squeue", &given)
+            .env("PATH", path)
+            .output()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let output = check_output("squeue", output).unwrap();
+        let received: Vec<&str> = output.lines().collect();
+        assert_eq!(received[..2], ["--clusters", "beta"]);
+        assert_eq!(received[2..], given);
+    }
+}
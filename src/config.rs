@@ -0,0 +1,332 @@
+use chrono::TimeDelta;
+use ratatui::style::{Color, Style};
+use std::{collections::HashMap, env, fs, io, path::PathBuf};
+
+// Settings are read from $XDG_CONFIG_HOME/lazyslurm/config.toml
+// (usually ~/.config/lazyslurm/config.toml). Everything is optional,
+// e.g.
+//
+//     # highlight running jobs this close to their time limit
+//     timeout_warning_minutes = 30
+//
+//     # highlight jobs using less than this much of their CPU/memory
+//     efficiency_warning_percent = 50
+//
+//     # colours for each job state (as in StateCompact)
+//     [theme.states]
+//     R = "green"
+//     PD = "#ffaf00"
+//
+//     # run squeue etc. on a login node over SSH
+//     host = "login.cluster.ac.uk"
+//
+//     # talk to slurmrestd over HTTP instead of running squeue etc.
+//     [slurmrestd]
+//     url = "http://slurm.example.com:6820"
+//     token = "..."         # a JWT, SLURM_JWT takes precedence
+//     user = "alice"        # optional, if not in the token
+//     api_version = "v0.0.40"
+//
+//     # or several clusters, each local (run with --clusters NAME) or on
+//     # a login node over SSH, instead of host/slurmrestd
+//     [[clusters]]
+//     name = "alpha"
+//     [[clusters]]
+//     name = "beta"
+//     host = "login.beta.ac.uk"
+//     cluster = "beta"      # optional, for --clusters on that host
+//
+//     # the made up cluster used with --simulate
+//     [simulator]
+//     nodes = 8
+//     gpus_per_node = 4
+//     gpu_type = "a100"
+//     partitions = ["gpu", "debug"]
+//     users = ["alice", "bob", "carol"]
+//     jobs = 40             # how busy to keep the queue
+//     seed = 1              # for the same jobs every time
+//
+pub struct Config {
+    pub timeout_warning: TimeDelta,
+    pub efficiency_warning: f64,
+    pub theme: Theme,
+    pub host: Option<String>,
+    pub slurmrestd: Option<SlurmRestdConfig>,
+    pub clusters: Vec<ClusterConfig>,
+    pub simulator: SimulatorConfig,
+}
+
+pub struct ClusterConfig {
+    pub name: String,
+    pub host: Option<String>,
+    pub cluster: Option<String>,
+}
+
+pub struct SlurmRestdConfig {
+    pub url: String,
+    pub token: Option<String>,
+    pub user: Option<String>,
+    pub api_version: String,
+}
+
+impl SlurmRestdConfig {
+    pub fn new(url: String) -> Self {
+        SlurmRestdConfig {
+            url,
+            token: None,
+            user: None,
+            api_version: "v0.0.40".to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SimulatorConfig {
+    pub nodes: u32,
+    pub gpus_per_node: u32,
+    pub gpu_type: String,
+    pub partitions: Vec<String>,
+    pub users: Vec<String>,
+    pub jobs: u32,
+    pub seed: Option<u64>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        SimulatorConfig {
+            nodes: 8,
+            gpus_per_node: 4,
+            gpu_type: "a100".to_string(),
+            partitions: vec!["gpu".to_string(), "debug".to_string()],
+            users: ["alice", "bob", "carol", "dave", "erin"]
+                .map(String::from)
+                .to_vec(),
+            jobs: 40,
+            seed: None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            timeout_warning: TimeDelta::minutes(30),
+            efficiency_warning: 50.0,
+            theme: Theme::default(),
+            host: None,
+            slurmrestd: None,
+            clusters: vec![],
+            simulator: SimulatorConfig::default(),
+        }
+    }
+}
+
+pub struct Theme {
+    states: HashMap<String, Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let states = [
+            ("R", Color::Green),
+            ("PD", Color::Yellow),
+            ("CG", Color::Cyan),
+            ("F", Color::Red),
+            ("TO", Color::Red),
+            ("OOM", Color::Red),
+            ("NF", Color::Red),
+            ("S", Color::Gray),
+            ("ST", Color::Gray),
+        ]
+        .into_iter()
+        .map(|(state, colour)| (state.to_string(), colour))
+        .collect();
+        Theme { states }
+    }
+}
+
+impl Theme {
+    // Style for a job state, given in the short form used by
+    // StateCompact, e.g. R or PD.
+    pub fn state_style(&self, state: &str) -> Style {
+        match self.states.get(state) {
+            Some(colour) => Style::default().fg(*colour),
+            None => Style::default(),
+        }
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("lazyslurm")),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/lazyslurm")),
+    }
+}
+
+// An optional whole number in a section of the config
+fn get_number(section: &toml::Value, name: &str, key: &str) -> Result<Option<u64>, String> {
+    match section.get(key) {
+        Some(value) => match value.as_integer().and_then(|n| u64::try_from(n).ok()) {
+            Some(n) => Ok(Some(n)),
+            None => Err(format!("{}.{} should be a whole number", name, key)),
+        },
+        None => Ok(None),
+    }
+}
+
+// An optional, non-empty list of strings in a section of the config
+fn get_strings(
+    section: &toml::Value,
+    name: &str,
+    key: &str,
+) -> Result<Option<Vec<String>>, String> {
+    let Some(value) = section.get(key) else {
+        return Ok(None);
+    };
+    let strings: Option<Vec<String>> = value
+        .as_array()
+        .and_then(|list| list.iter().map(|s| s.as_str().map(String::from)).collect());
+    match strings {
+        Some(strings) if !strings.is_empty() => Ok(Some(strings)),
+        _ => Err(format!("{}.{} should be a list of strings", name, key)),
+    }
+}
+
+// An optional string in a section of the config
+fn get_string(section: &toml::Value, name: &str, key: &str) -> Result<Option<String>, String> {
+    match section.get(key) {
+        Some(value) => match value.as_str() {
+            Some(s) => Ok(Some(s.to_string())),
+            None => Err(format!("{}.{} should be a string", name, key)),
+        },
+        None => Ok(None),
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                Config::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let table: toml::Table = contents.parse().map_err(|e| format!("{}", e))?;
+        let mut config = Config::default();
+
+        if let Some(value) = table.get("timeout_warning_minutes") {
+            config.timeout_warning = value
+                .as_integer()
+                .filter(|&minutes| minutes >= 0)
+                .and_then(TimeDelta::try_minutes)
+                .ok_or("timeout_warning_minutes should be a whole number of minutes")?;
+        }
+
+        if let Some(value) = table.get("efficiency_warning_percent") {
+            config.efficiency_warning = value
+                .as_float()
+                .or(value.as_integer().map(|i| i as f64))
+                .ok_or("efficiency_warning_percent should be a number")?;
+        }
+
+        let states = table
+            .get("theme")
+            .and_then(|theme| theme.get("states"))
+            .and_then(|states| states.as_table());
+        for (state, colour) in states.into_iter().flatten() {
+            let colour = colour
+                .as_str()
+                .and_then(|c| c.parse::<Color>().ok())
+                .ok_or(format!("invalid colour for state {}", state))?;
+            config.theme.states.insert(state.to_string(), colour);
+        }
+
+        if let Some(value) = table.get("host") {
+            let host = value.as_str().ok_or("host should be a string")?;
+            config.host = Some(host.to_string());
+        }
+
+        if let Some(slurmrestd) = table.get("slurmrestd") {
+            let get = |key| get_string(slurmrestd, "slurmrestd", key);
+            let url = get("url")?.ok_or("slurmrestd.url is required")?;
+            let mut rest = SlurmRestdConfig::new(url);
+            rest.token = get("token")?;
+            rest.user = get("user")?;
+            if let Some(version) = get("api_version")? {
+                rest.api_version = version;
+            }
+            config.slurmrestd = Some(rest);
+        }
+
+        let clusters = match table.get("clusters") {
+            Some(clusters) => clusters
+                .as_array()
+                .ok_or("clusters should be a list of [[clusters]] tables")?
+                .as_slice(),
+            None => &[],
+        };
+        for cluster in clusters {
+            let get = |key| get_string(cluster, "clusters", key);
+            let name = get("name")?.ok_or("every cluster needs a name")?;
+            // a local cluster is picked with --clusters, by its own name
+            let host = get("host")?;
+            let slurm_cluster = match (get("cluster")?, &host) {
+                (Some(c), _) => Some(c),
+                (None, None) => Some(name.clone()),
+                (None, Some(_)) => None,
+            };
+            config.clusters.push(ClusterConfig {
+                name,
+                host,
+                cluster: slurm_cluster,
+            });
+        }
+        if let Some(simulator) = table.get("simulator") {
+            let sim = &mut config.simulator;
+            let number = |key| get_number(simulator, "simulator", key);
+            let to_u32 = |n: u64| u32::try_from(n).map_err(|e| e.to_string());
+            if let Some(nodes) = number("nodes")? {
+                sim.nodes = to_u32(nodes.max(1))?;
+            }
+            if let Some(gpus) = number("gpus_per_node")? {
+                sim.gpus_per_node = to_u32(gpus)?;
+            }
+            if let Some(jobs) = number("jobs")? {
+                sim.jobs = to_u32(jobs)?;
+            }
+            sim.seed = number("seed")?;
+            if let Some(gpu_type) = get_string(simulator, "simulator", "gpu_type")? {
+                sim.gpu_type = gpu_type;
+            }
+            if let Some(partitions) = get_strings(simulator, "simulator", "partitions")? {
+                sim.partitions = partitions;
+            }
+            if let Some(users) = get_strings(simulator, "simulator", "users")? {
+                sim.users = users;
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_warning() {
+        let config = Config::parse("timeout_warning_minutes = 10").unwrap();
+        assert_eq!(config.timeout_warning, TimeDelta::minutes(10));
+        for minutes in ["-1", "9223372036854775807", "\"10\""] {
+            let contents = format!("timeout_warning_minutes = {}", minutes);
+            assert!(Config::parse(&contents).is_err());
+        }
+    }
+}
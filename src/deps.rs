@@ -0,0 +1,231 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::jobs::Job;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DependencyStatus {
+    Waiting,
+    Failed,
+    // Slurm usually drops dependencies from the list once they are
+    // satisfied, so anything not marked otherwise is taken to be done
+    Satisfied,
+}
+
+// One condition of a job's dependency list, e.g. afterok:1234
+pub struct Dependency {
+    pub kind: String,
+    pub job_id: String,
+    pub status: DependencyStatus,
+}
+
+// A job in the dependency graph, along with the jobs it is waiting on
+// (when walking upstream) or the jobs waiting on it (downstream).
+pub struct DependencyNode {
+    pub kind: String,
+    pub job_id: String,
+    pub status: DependencyStatus,
+    pub children: Vec<DependencyNode>,
+}
+
+static DEPENDENCY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\w+):(\d+(?:_(?:\d+|\*))?)(?:\+\d+)?(?:\((\w+)\))?").unwrap());
+
+// squeue shows dependencies like afterok:1234(unfulfilled),afterany:99_*(failed)
+// with conditions separated by , (all of) or ? (any of).
+pub fn parse_dependencies(dependency: &str) -> Vec<Dependency> {
+    DEPENDENCY_RE
+        .captures_iter(dependency)
+        .map(|caps| Dependency {
+            kind: caps[1].to_string(),
+            job_id: caps[2].to_string(),
+            status: match caps.get(3).map(|m| m.as_str()) {
+                Some("unfulfilled") => DependencyStatus::Waiting,
+                Some("failed") => DependencyStatus::Failed,
+                _ => DependencyStatus::Satisfied,
+            },
+        })
+        .collect()
+}
+
+// Whether a dependency on `job_id` is about this job. Dependencies can
+// be on a single array task (1234_5) or on the whole array (1234_*).
+pub fn refers_to(job_id: &str, job: &Job) -> bool {
+    job_id == job.JobID
+        || job_id == job.ArrayJobID
+        || job_id.strip_suffix("_*") == Some(job.ArrayJobID.as_str())
+}
+
+// dependency chains can be long, and in theory circular
+const MAX_DEPTH: usize = 8;
+
+pub fn get_upstream(job: &Job, jobs: &[&Job]) -> Vec<DependencyNode> {
+    upstream(job, jobs, 0)
+}
+
+fn upstream(job: &Job, jobs: &[&Job], depth: usize) -> Vec<DependencyNode> {
+    parse_dependencies(&job.Dependency)
+        .into_iter()
+        .map(|dep| {
+            let children = match jobs.iter().find(|j| refers_to(&dep.job_id, j)) {
+                Some(upstream_job) if depth < MAX_DEPTH => upstream(upstream_job, jobs, depth + 1),
+                _ => vec![],
+            };
+            DependencyNode {
+                kind: dep.kind,
+                job_id: dep.job_id,
+                status: dep.status,
+                children,
+            }
+        })
+        .collect()
+}
+
+pub fn get_downstream(job: &Job, jobs: &[&Job]) -> Vec<DependencyNode> {
+    downstream(job, jobs, 0)
+}
+
+fn downstream(job: &Job, jobs: &[&Job], depth: usize) -> Vec<DependencyNode> {
+    let mut nodes: Vec<DependencyNode> = vec![];
+    for downstream_job in jobs {
+        // all tasks of an array share its dependencies, so
+        // list the array once rather than every single task
+        let job_id = match downstream_job.ArrayTaskID.as_str() {
+            "N/A" => downstream_job.JobID.clone(),
+            _ => format!("{}_*", downstream_job.ArrayJobID),
+        };
+        if nodes.iter().any(|n| n.job_id == job_id) {
+            continue;
+        }
+        for dep in parse_dependencies(&downstream_job.Dependency) {
+            if !refers_to(&dep.job_id, job) {
+                continue;
+            }
+            let children = if depth < MAX_DEPTH {
+                downstream(downstream_job, jobs, depth + 1)
+            } else {
+                vec![]
+            };
+            nodes.push(DependencyNode {
+                kind: dep.kind,
+                job_id: job_id.clone(),
+                status: dep.status,
+                children,
+            });
+        }
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::squeue_json::parse_jobs;
+
+    // a running job 1001, job 1002 waiting on it, and an array 1010
+    fn jobs() -> Vec<Job> {
+        parse_jobs(include_str!("../tests/fixtures/squeue-23.02.json")).unwrap()
+    }
+
+    fn job_with(jobs: &[Job], job_id: &str, dependency: &str) -> Job {
+        let mut job = jobs[0].clone();
+        job.JobID = job_id.to_string();
+        job.ArrayJobID = job_id.to_string();
+        job.ArrayTaskID = "N/A".to_string();
+        job.Dependency = dependency.to_string();
+        job
+    }
+
+    fn depth(nodes: &[DependencyNode]) -> usize {
+        nodes
+            .iter()
+            .map(|n| 1 + depth(&n.children))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn dependencies() {
+        let deps =
+            parse_dependencies("afterok:1234+10(unfulfilled),afterany:99_*(failed)?aftercorr:7_3");
+        let deps: Vec<(&str, &str, DependencyStatus)> = deps
+            .iter()
+            .map(|d| (d.kind.as_str(), d.job_id.as_str(), d.status))
+            .collect();
+        assert_eq!(
+            deps,
+            [
+                ("afterok", "1234", DependencyStatus::Waiting),
+                ("afterany", "99_*", DependencyStatus::Failed),
+                ("aftercorr", "7_3", DependencyStatus::Satisfied),
+            ]
+        );
+        assert!(parse_dependencies("(null)").is_empty());
+        assert!(parse_dependencies("").is_empty());
+    }
+
+    #[test]
+    fn upstream_and_downstream() {
+        let jobs = jobs();
+        let all: Vec<&Job> = jobs.iter().collect();
+        let running = jobs.iter().find(|j| j.JobID == "1001").unwrap();
+        let waiting = jobs.iter().find(|j| j.JobID == "1002").unwrap();
+
+        let upstream = get_upstream(waiting, &all);
+        assert_eq!(upstream.len(), 1);
+        assert_eq!(upstream[0].job_id, "1001");
+        assert_eq!(upstream[0].status, DependencyStatus::Waiting);
+
+        let downstream = get_downstream(running, &all);
+        assert_eq!(downstream.len(), 1);
+        assert_eq!(downstream[0].job_id, "1002");
+        assert!(get_downstream(waiting, &all).is_empty());
+    }
+
+    #[test]
+    fn arrays_are_listed_once() {
+        let mut jobs = jobs();
+        jobs.push(job_with(&jobs, "2000", "afterany:1010_*(unfulfilled)"));
+        let all: Vec<&Job> = jobs.iter().collect();
+        let task = jobs.iter().find(|j| j.JobID == "1010_1").unwrap();
+        let after_array = jobs.iter().find(|j| j.JobID == "2000").unwrap();
+
+        assert!(refers_to("1010_*", task));
+        assert!(refers_to("1010_1", task));
+        assert!(!refers_to("1010_3", task));
+        assert_eq!(get_downstream(task, &all)[0].job_id, "2000");
+        assert_eq!(get_upstream(after_array, &all)[0].job_id, "1010_*");
+
+        // when every task of the array waits on 1001
+        for job in jobs.iter_mut().filter(|j| j.ArrayJobID == "1010") {
+            job.Dependency = "afterok:1001(unfulfilled)".to_string();
+        }
+        let all: Vec<&Job> = jobs.iter().collect();
+        let running = jobs.iter().find(|j| j.JobID == "1001").unwrap();
+        let ids: Vec<String> = get_downstream(running, &all)
+            .into_iter()
+            .map(|n| n.job_id)
+            .collect();
+        assert_eq!(ids, ["1002", "1010_*"]);
+    }
+
+    #[test]
+    fn long_and_circular_chains_stop() {
+        let jobs = jobs();
+        // 1 <- 2 <- ... <- 20
+        let chain: Vec<Job> = (1..=20)
+            .map(|i| job_with(&jobs, &i.to_string(), &format!("afterok:{}", i - 1)))
+            .collect();
+        let all: Vec<&Job> = chain.iter().collect();
+        assert_eq!(depth(&get_upstream(&chain[19], &all)), MAX_DEPTH + 1);
+        assert_eq!(depth(&get_downstream(&chain[0], &all)), MAX_DEPTH + 1);
+
+        let circle = [
+            job_with(&jobs, "1", "afterok:2"),
+            job_with(&jobs, "2", "afterok:1"),
+        ];
+        let all: Vec<&Job> = circle.iter().collect();
+        assert_eq!(depth(&get_upstream(&circle[0], &all)), MAX_DEPTH + 1);
+        assert_eq!(depth(&get_downstream(&circle[0], &all)), MAX_DEPTH + 1);
+    }
+}
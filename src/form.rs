@@ -0,0 +1,75 @@
+use crossterm::event::KeyEvent;
+use tui_textarea::TextArea;
+
+pub struct FormField<'a> {
+    pub label: String,
+    pub original: String,
+    pub input: TextArea<'a>,
+}
+
+impl FormField<'_> {
+    pub fn value(&self) -> String {
+        self.input.lines().concat().trim().to_string()
+    }
+}
+
+// A popup with a labelled, single line text box for each field,
+// pre-filled with the current values.
+pub struct Form<'a> {
+    pub title: String,
+    pub fields: Vec<FormField<'a>>,
+    pub focus: usize,
+}
+
+impl<'a> Form<'a> {
+    pub fn new(title: String, fields: Vec<(String, String)>) -> Self {
+        let fields = fields
+            .into_iter()
+            .map(|(label, value)| FormField {
+                label,
+                input: TextArea::new(vec![value.clone()]),
+                original: value,
+            })
+            .collect();
+        Form {
+            title,
+            fields,
+            focus: 0,
+        }
+    }
+
+    // a form without fields only asks for confirmation
+    pub fn next_field(&mut self) {
+        if !self.fields.is_empty() {
+            self.focus = (self.focus + 1) % self.fields.len();
+        }
+    }
+
+    pub fn previous_field(&mut self) {
+        if !self.fields.is_empty() {
+            self.focus = (self.focus + self.fields.len() - 1) % self.fields.len();
+        }
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        if let Some(field) = self.fields.get_mut(self.focus) {
+            field.input.input(key);
+        }
+    }
+
+    pub fn values(&self) -> Vec<(String, String)> {
+        self.fields
+            .iter()
+            .map(|f| (f.label.clone(), f.value()))
+            .collect()
+    }
+
+    // The fields the user has changed, with their new values
+    pub fn changed(&self) -> Vec<(String, String)> {
+        self.fields
+            .iter()
+            .filter(|f| f.value() != f.original.trim())
+            .map(|f| (f.label.clone(), f.value()))
+            .collect()
+    }
+}
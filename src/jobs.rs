@@ -1,6 +1,12 @@
+use chrono::{NaiveDateTime, TimeDelta};
 use regex::{Regex, RegexBuilder};
-use std::collections::HashMap;
-use std::{io::BufRead, process::Command};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+
+use crate::command::Runner;
+use crate::squeue_json::parse_jobs;
+use crate::time::{parse_duration, parse_timestamp};
 
 // This macro is a bit crazy.
 // the reason we have it is because the names of the fields were
@@ -14,6 +20,7 @@ macro_rules! make_field_names_available {
         $($field_name:ident: $field_type:ty,)*
     }) => {
         #[allow(non_snake_case)]
+        #[derive(Clone)]
         pub struct $name {
             $(pub $field_name: $field_type,)*
         }
@@ -44,19 +51,25 @@ make_field_names_available!(
         Reason: String,
         Name: String,
         UserName: String,
+        Account: String,
+        QOS: String,
+        Cluster: String,
         JobID: String,
         ArrayJobID: String,
         ArrayTaskID: String,
         Partition: String,
         NodeList: String,
         ReqNodes: String,
+        ExcNodes: String,
         SubmitTime: String,
         StartTime: String,
         TimeLimit: String,
         TimeUsed: String,
         TRES: String,
+        Dependency: String,
         NumTasks: String,
         Priority: String,
+        Nice: String,
         WorkDir: String,
         Command: String,
         STDOUT: String,
@@ -64,7 +77,51 @@ make_field_names_available!(
     }
 );
 
-pub fn get_jobs(filter_re: &String) -> Vec<Job> {
+// Whether squeue can print JSON, from its version, e.g. "slurm 23.02.7"
+pub fn squeue_supports_json(runner: &Runner) -> bool {
+    let Ok(version) = runner.run("squeue", &["--version"]) else {
+        return false;
+    };
+    let mut numbers = version
+        .split_whitespace()
+        .last()
+        .unwrap_or_default()
+        .split('.')
+        .map(|n| n.parse::<u32>().unwrap_or(0));
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+    (major, minor) >= (21, 8)
+}
+
+// `use_json` is decided once at startup with squeue_supports_json, and
+// turned off if JSON turns out not to work (e.g. when Slurm was built
+// without its JSON plugin).
+pub fn get_jobs(
+    runner: &Runner,
+    use_json: &AtomicBool,
+    finished: bool,
+) -> Result<Vec<Job>, String> {
+    if use_json.load(Ordering::Relaxed) {
+        // squeue --json ignores --states, and lists every job it knows
+        match runner
+            .run("squeue", &["--json"])
+            .and_then(|json| parse_jobs(&json))
+        {
+            Ok(mut jobs) => {
+                jobs.retain(|j| finished || !is_finished(&j.State));
+                return Ok(jobs);
+            }
+            Err(_) => use_json.store(false, Ordering::Relaxed),
+        }
+    }
+    get_jobs_text(runner, finished)
+}
+
+// Older versions of squeue have no JSON output, so fields are printed
+// with a separator between them instead. Lines which do not split into
+// the right number of fields (e.g. a job name containing the separator)
+// are skipped.
+fn get_jobs_text(runner: &Runner, finished: bool) -> Result<Vec<Job>, String> {
     let output_separator = "###";
     let fields = Job::field_names().to_owned();
     let output_format: Vec<String> = fields
@@ -73,41 +130,102 @@ pub fn get_jobs(filter_re: &String) -> Vec<Job> {
         .collect();
     let format_str: String = output_format.join(",");
 
-    let re = RegexBuilder::new(filter_re)
-        .case_insensitive(true)
-        .build()
-        // if invalid regex, just use ""
-        .unwrap_or(RegexBuilder::new("").build().unwrap());
-
-    let jobs: Vec<Job> = Command::new("squeue")
-        .arg("--array")
-        .arg("--noheader")
-        .arg("--Format")
-        .arg(format_str)
-        .output()
-        .expect("failed to execute squeue")
-        .stdout
+    let mut args = vec!["--array", "--noheader", "--Format", &format_str];
+    if finished {
+        args.push("--states=all");
+    }
+    let jobs: Vec<Job> = runner
+        .run("squeue", &args)?
         .lines()
-        .map(|l| l.unwrap().trim().to_string())
+        .map(|l| l.trim().to_string())
         .filter_map(|l| {
-            if !re.is_match(l.as_str()) {
-                return None;
-            }
             let parts: Vec<_> = l.split(output_separator).collect();
             if parts.len() != fields.len() + 1 {
                 return None;
             }
             let mut job = Job::from_str_parts(parts);
             parse_paths(&mut job);
-            return Some(job);
+            Some(job)
         })
         .collect();
-    jobs
+    Ok(jobs)
+}
+
+pub fn filter_regex(filter_re: &str) -> Result<Regex, String> {
+    RegexBuilder::new(filter_re)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// Jobs with any details matching the regex, in the order they are
+// shown in the Details panel.
+pub fn filter_jobs(jobs: &[Job], filter_re: &str) -> Vec<Job> {
+    // while the filter is being typed it is often invalid,
+    // so until it is valid again just show everything
+    let re = filter_regex(filter_re).unwrap_or(Regex::new("").unwrap());
+    matching_jobs(jobs, &re)
+}
+
+pub fn matching_jobs(jobs: &[Job], re: &Regex) -> Vec<Job> {
+    jobs.iter()
+        .filter(|j| re.is_match(&j.field_values().join("###")))
+        .cloned()
+        .collect()
+}
+
+// The short form of a job state, as used by StateCompact. sacct adds
+// extra detail to some states, e.g. "CANCELLED by 1234".
+pub fn compact_state(state: &str) -> &str {
+    match state.split_whitespace().next().unwrap_or("") {
+        "BOOT_FAIL" => "BF",
+        "CANCELLED" => "CA",
+        "COMPLETED" => "CD",
+        "COMPLETING" => "CG",
+        "CONFIGURING" => "CF",
+        "DEADLINE" => "DL",
+        "FAILED" => "F",
+        "NODE_FAIL" => "NF",
+        "OUT_OF_MEMORY" => "OOM",
+        "PENDING" => "PD",
+        "PREEMPTED" => "PR",
+        "REQUEUED" => "RQ",
+        "REQUEUE_FED" => "RF",
+        "REQUEUE_HOLD" => "RH",
+        "RESIZING" => "RS",
+        "RESV_DEL_HOLD" => "RD",
+        "REVOKED" => "RV",
+        "RUNNING" => "R",
+        "SIGNALING" => "SI",
+        "SPECIAL_EXIT" => "SE",
+        "STAGE_OUT" => "SO",
+        "STOPPED" => "ST",
+        "SUSPENDED" => "S",
+        "TIMEOUT" => "TO",
+        other => other,
+    }
+}
+
+// Whether a job has stopped running for good, one way or another.
+pub fn is_finished(state: &str) -> bool {
+    matches!(
+        state.split_whitespace().next().unwrap_or(""),
+        "BOOT_FAIL"
+            | "CANCELLED"
+            | "COMPLETED"
+            | "DEADLINE"
+            | "FAILED"
+            | "NODE_FAIL"
+            | "OUT_OF_MEMORY"
+            | "PREEMPTED"
+            | "SPECIAL_EXIT"
+            | "TIMEOUT"
+    )
 }
 
 // STDOUT as retrieved by squeue looks like this: slurm.%N.%j.log,
 // we need to interpolate the terms into the actual path.
-fn parse_paths(job: &mut Job) {
+pub fn parse_paths(job: &mut Job) {
     job.STDOUT = interpolate_path(&job.STDOUT, job);
     job.STDERR = interpolate_path(&job.STDERR, job);
 }
@@ -130,7 +248,7 @@ fn interpolate_path(pattern: &str, job: &Job) -> String {
                     _ => {
                         let mut pad_specifier = String::new();
                         while let Some(d) = chars.peek() {
-                            if d.is_digit(10) {
+                            if d.is_ascii_digit() {
                                 pad_specifier.push(*d);
                                 chars.next();
                             } else {
@@ -162,10 +280,11 @@ fn interpolate_path(pattern: &str, job: &Job) -> String {
 // see `man squeue` for all valid interpolations
 fn replace_char(symbol: char, job: &Job) -> Option<String> {
     match symbol {
-        // NB this does not cover some valid cases (A,J,n,s,t)
+        // NB this does not cover some valid cases (J,n,s,t)
         // because I'm not too sure how to do them atm.
         // this should be enough for most cases.
-        'a' => Some(job.ArrayJobID.to_owned()),
+        'A' => Some(job.ArrayJobID.to_owned()),
+        'a' => Some(job.ArrayTaskID.to_owned()),
         'N' => Some(job.NodeList.to_owned()),
         'u' => Some(job.UserName.to_owned()),
         'x' => Some(job.Name.to_owned()),
@@ -174,8 +293,196 @@ fn replace_char(symbol: char, job: &Job) -> Option<String> {
     }
 }
 
+// How much time a running job has left before it hits its time limit
+// and gets killed, along with the time limit itself.
+pub fn get_time_remaining(job: &Job) -> Option<(TimeDelta, TimeDelta)> {
+    if job.State != "RUNNING" {
+        return None;
+    }
+    let used = parse_duration(&job.TimeUsed)?;
+    let limit = parse_duration(&job.TimeLimit)?;
+    Some(((limit - used).max(TimeDelta::zero()), limit))
+}
+
+// Expand a compressed Slurm hostlist such as `gpu[01-03,07],cpu1`
+// into the individual node names.
+pub fn expand_hostlist(hostlist: &str) -> Vec<String> {
+    let mut hosts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in hostlist.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                hosts.extend(expand_host(&hostlist[start..i]));
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    hosts.extend(expand_host(&hostlist[start..]));
+    hosts
+}
+
+fn expand_host(host: &str) -> Vec<String> {
+    // anything malformed, e.g. a ] before the [, is kept as it is
+    let Some((open, close)) = host
+        .find('[')
+        .and_then(|open| Some((open, open + host[open..].find(']')?)))
+    else {
+        if host.is_empty() {
+            return vec![];
+        }
+        return vec![host.to_string()];
+    };
+    let prefix = &host[..open];
+    let suffixes = expand_host(&host[close + 1..]);
+    let suffixes = if suffixes.is_empty() {
+        vec![String::new()]
+    } else {
+        suffixes
+    };
+
+    let mut hosts = Vec::new();
+    for range in host[open + 1..close].split(',') {
+        let ids: Vec<String> = match range.split_once('-') {
+            Some((lo, hi)) => match (lo.parse::<u32>(), hi.parse::<u32>()) {
+                (Ok(l), Ok(h)) if l <= h => (l..=h)
+                    .map(|n| format!("{:0width$}", n, width = lo.len()))
+                    .collect(),
+                _ => vec![range.to_string()],
+            },
+            None => vec![range.to_string()],
+        };
+        for id in ids {
+            for suffix in &suffixes {
+                hosts.push(format!("{}{}{}", prefix, id, suffix));
+            }
+        }
+    }
+    hosts
+}
+
+static GENERIC_GPU_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"gres/gpu=(\d+)").unwrap());
+static SPECIFIC_GPU_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"gres/gpu:(\w+)=(\d+)").unwrap());
+
+// GPUs in a TRES string, by type. Jobs which did not ask for a
+// specific type (gres/gpu=N) are reported under the type "any".
+pub fn get_gpus(tres: &str) -> Vec<(String, u32)> {
+    let gpus: Vec<(String, u32)> = SPECIFIC_GPU_RE
+        .captures_iter(tres)
+        .map(|caps| (caps[1].to_string(), caps[2].parse().unwrap_or(0)))
+        .collect();
+    if !gpus.is_empty() {
+        return gpus;
+    }
+    match GENERIC_GPU_RE.captures(tres) {
+        Some(caps) => vec![("any".to_string(), caps[1].parse().unwrap_or(0))],
+        None => vec![],
+    }
+}
+
 #[derive(Clone, Debug, Default)]
-pub struct UserStats {
+pub struct ArrayStats {
+    pub running_tasks: u32,
+    pub pending_tasks: u32,
+    pub done_tasks: u32,
+    pub gpus_used: u32,
+}
+
+pub fn get_array_stats<'a>(tasks: impl Iterator<Item = &'a Job>) -> ArrayStats {
+    let mut stats = ArrayStats::default();
+    for task in tasks {
+        match task.State.as_str() {
+            "RUNNING" => {
+                stats.running_tasks += 1;
+                stats.gpus_used += get_gpus(&task.TRES).iter().map(|(_, n)| n).sum::<u32>();
+            }
+            "PENDING" => stats.pending_tasks += 1,
+            _ => stats.done_tasks += 1,
+        }
+    }
+    stats
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupBy {
+    #[default]
+    User,
+    Partition,
+    Account,
+    Qos,
+    Node,
+    GpuType,
+    Cluster,
+}
+
+impl GroupBy {
+    pub fn next(self) -> Self {
+        match self {
+            GroupBy::User => GroupBy::Partition,
+            GroupBy::Partition => GroupBy::Account,
+            GroupBy::Account => GroupBy::Qos,
+            GroupBy::Qos => GroupBy::Node,
+            GroupBy::Node => GroupBy::GpuType,
+            GroupBy::GpuType => GroupBy::Cluster,
+            GroupBy::Cluster => GroupBy::User,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::User => "User",
+            GroupBy::Partition => "Partition",
+            GroupBy::Account => "Account",
+            GroupBy::Qos => "QOS",
+            GroupBy::Node => "Node",
+            GroupBy::GpuType => "GPU type",
+            GroupBy::Cluster => "Cluster",
+        }
+    }
+
+    // The groups a job belongs to, along with the number of GPUs it
+    // uses in each. A job can be in several groups at once, e.g. when
+    // it spans several nodes, in which case its GPUs are split evenly.
+    fn groups(self, job: &Job) -> Vec<(String, u32)> {
+        let gpus = get_gpus(&job.TRES);
+        let total_gpus: u32 = gpus.iter().map(|(_, n)| n).sum();
+        match self {
+            GroupBy::User => vec![(job.UserName.clone(), total_gpus)],
+            GroupBy::Partition => vec![(job.Partition.clone(), total_gpus)],
+            GroupBy::Account => vec![(job.Account.clone(), total_gpus)],
+            GroupBy::Qos => vec![(job.QOS.clone(), total_gpus)],
+            GroupBy::Cluster => vec![(job.Cluster.clone(), total_gpus)],
+            GroupBy::Node => {
+                let nodes = expand_hostlist(&job.NodeList);
+                if nodes.is_empty() {
+                    return vec![("(unassigned)".to_string(), total_gpus)];
+                }
+                let n = nodes.len() as u32;
+                nodes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, node)| {
+                        let share = total_gpus / n + u32::from((i as u32) < total_gpus % n);
+                        (node, share)
+                    })
+                    .collect()
+            }
+            GroupBy::GpuType => {
+                if gpus.is_empty() {
+                    return vec![("none".to_string(), 0)];
+                }
+                gpus
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GroupStats {
     pub name: String,
     pub running_jobs: u32,
     pub pending_jobs: u32,
@@ -187,15 +494,17 @@ pub struct ClusterOverview {
     pub jobs_running: u32,
     pub jobs_pending: u32,
     pub jobs_completing: u32,
-    pub user_stats: Vec<UserStats>,
+    pub group_by: GroupBy,
+    pub group_stats: Vec<GroupStats>,
 }
 
-pub fn get_cluster_overview(jobs: &Vec<Job>) -> ClusterOverview {
-    let mut overview = ClusterOverview::default();
-    let mut user_stats_map: HashMap<String, UserStats> = HashMap::new();
+pub fn get_cluster_overview(jobs: &[Job], group_by: GroupBy) -> ClusterOverview {
+    let mut overview = ClusterOverview {
+        group_by,
+        ..Default::default()
+    };
+    let mut group_stats_map: HashMap<String, GroupStats> = HashMap::new();
 
-    let generic_gpu_re = Regex::new(r"gres/gpu=(\d+)").unwrap();
-    let specific_gpu_re = Regex::new(r"gres/gpu:(\w+)=(\d+)").unwrap();
     for job in jobs {
         match job.State.as_str() {
             "RUNNING" => overview.jobs_running += 1,
@@ -204,42 +513,189 @@ pub fn get_cluster_overview(jobs: &Vec<Job>) -> ClusterOverview {
             _ => (),
         }
 
-        let user_stats = user_stats_map
-            .entry(job.UserName.clone())
-            .or_insert_with(|| UserStats {
-                name: job.UserName.clone(),
-                ..Default::default()
-            });
-
-        if job.State == "RUNNING" {
-            user_stats.running_jobs += 1;
-            let mut total_gpus_for_job = 0;
-
-            for caps in specific_gpu_re.captures_iter(&job.TRES) {
-                let count: u32 = caps.get(2).map_or(0, |m| m.as_str().parse().unwrap_or(0));
-                total_gpus_for_job += count;
-            }
+        for (name, gpus) in group_by.groups(job) {
+            let group_stats = group_stats_map
+                .entry(name.clone())
+                .or_insert_with(|| GroupStats {
+                    name,
+                    ..Default::default()
+                });
 
-            if total_gpus_for_job == 0 {
-                if let Some(caps) = generic_gpu_re.captures(&job.TRES) {
-                    if let Some(count_match) = caps.get(1) {
-                        let count: u32 = count_match.as_str().parse().unwrap_or(0);
-                        total_gpus_for_job = count;
-                    }
-                }
+            if job.State == "RUNNING" {
+                group_stats.running_jobs += 1;
+                group_stats.gpus_used += gpus;
+            } else if job.State == "PENDING" {
+                group_stats.pending_jobs += 1;
             }
-            user_stats.gpus_used += total_gpus_for_job;
-        } else if job.State == "PENDING" {
-            user_stats.pending_jobs += 1;
         }
     }
 
-    let mut user_stats_vec: Vec<UserStats> = user_stats_map.into_values().collect();
-    user_stats_vec.sort_by(|a, b| {
+    let mut group_stats_vec: Vec<GroupStats> = group_stats_map.into_values().collect();
+    group_stats_vec.sort_by(|a, b| {
         b.gpus_used
             .cmp(&a.gpus_used)
             .then_with(|| a.name.cmp(&b.name))
     });
-    overview.user_stats = user_stats_vec;
+    overview.group_stats = group_stats_vec;
     overview
 }
+
+#[derive(Clone, Debug, Default)]
+pub struct ReasonStats {
+    pub reason: String,
+    pub pending_jobs: u32,
+    pub users: Vec<(String, u32)>,
+}
+
+// Group pending jobs by why they are pending (Priority, Resources,
+// Dependency...), with a breakdown of how many jobs each user has.
+pub fn get_pending_reasons(jobs: &[Job]) -> Vec<ReasonStats> {
+    let mut reason_map: HashMap<String, HashMap<String, u32>> = HashMap::new();
+    for job in jobs.iter().filter(|j| j.State == "PENDING") {
+        *reason_map
+            .entry(job.Reason.clone())
+            .or_default()
+            .entry(job.UserName.clone())
+            .or_default() += 1;
+    }
+
+    let mut reasons: Vec<ReasonStats> = reason_map
+        .into_iter()
+        .map(|(reason, users)| {
+            let mut users: Vec<(String, u32)> = users.into_iter().collect();
+            users.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ReasonStats {
+                reason,
+                pending_jobs: users.iter().map(|(_, n)| n).sum(),
+                users,
+            }
+        })
+        .collect();
+    reasons.sort_by(|a, b| {
+        b.pending_jobs
+            .cmp(&a.pending_jobs)
+            .then_with(|| a.reason.cmp(&b.reason))
+    });
+    reasons
+}
+
+// For pending jobs, squeue reports the scheduler's estimate of when
+// the job will start in StartTime (the same as `squeue --start`).
+// These estimates get pushed back as higher priority jobs arrive, so
+// we keep track of how often that has happened for each job.
+#[derive(Clone, Debug)]
+pub struct StartEstimate {
+    pub start: NaiveDateTime,
+    pub slipped: u32,
+}
+
+// Jobs which have started or left the queue are forgotten, so `jobs`
+// should be the whole queue rather than the filtered list. Estimates
+// are keyed by cluster and job ID.
+pub fn update_start_estimates(
+    estimates: &mut HashMap<(String, String), StartEstimate>,
+    jobs: &[Job],
+) {
+    let pending: HashSet<(&str, &str)> = jobs
+        .iter()
+        .filter(|j| j.State == "PENDING")
+        .map(|j| (j.Cluster.as_str(), j.JobID.as_str()))
+        .collect();
+    estimates.retain(|(cluster, id), _| pending.contains(&(cluster.as_str(), id.as_str())));
+    for job in jobs.iter().filter(|j| j.State == "PENDING") {
+        let Some(start) = parse_timestamp(&job.StartTime) else {
+            continue;
+        };
+        estimates
+            .entry((job.Cluster.clone(), job.JobID.clone()))
+            .and_modify(|e| {
+                if start - e.start > TimeDelta::minutes(1) {
+                    e.slipped += 1;
+                }
+                e.start = start;
+            })
+            .or_insert(StartEstimate { start, slipped: 0 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, Replay};
+    use std::sync::Arc;
+
+    // squeue with and without --json, listing the same queue
+    fn runner(json: &str) -> Runner {
+        let format_str: Vec<String> = Job::field_names()
+            .iter()
+            .map(|f| format!("{}:###", f))
+            .collect();
+        let format_str = format_str.join(",");
+        let text = include_str!("../tests/fixtures/squeue.txt");
+        let text_args = ["squeue", "--array", "--noheader", "--Format", &format_str];
+        let replay = Replay::new(&[(&["squeue", "--json"], json), (&text_args, text)]);
+        Runner {
+            recording: Some(Arc::new(Recording::Replay(replay))),
+            ..Runner::default()
+        }
+    }
+
+    fn states(jobs: &[Job]) -> Vec<String> {
+        let mut states: Vec<String> = jobs
+            .iter()
+            .map(|j| format!("{} {} {} {}", j.JobID, j.ArrayJobID, j.ArrayTaskID, j.State))
+            .collect();
+        states.sort();
+        states
+    }
+
+    #[test]
+    fn json_and_text_list_the_same_jobs() {
+        for json in [
+            include_str!("../tests/fixtures/squeue-22.05.json"),
+            include_str!("../tests/fixtures/squeue-23.02.json"),
+        ] {
+            let runner = runner(json);
+            let from_json = get_jobs(&runner, &AtomicBool::new(true), false).unwrap();
+            let from_text = get_jobs(&runner, &AtomicBool::new(false), false).unwrap();
+            assert_eq!(states(&from_json), states(&from_text));
+
+            // the completed job is only there when asked for
+            let all = get_jobs(&runner, &AtomicBool::new(true), true).unwrap();
+            assert!(all
+                .iter()
+                .any(|j| j.JobID == "1003" && j.State == "COMPLETED"));
+        }
+    }
+
+    #[test]
+    fn falls_back_to_text() {
+        let runner = runner("squeue: error: Slurm was built without JSON support");
+        let use_json = AtomicBool::new(true);
+        assert_eq!(get_jobs(&runner, &use_json, false).unwrap().len(), 6);
+        assert!(!use_json.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn hostlists() {
+        assert_eq!(
+            expand_hostlist("gpu[01-03,07],cpu1"),
+            ["gpu01", "gpu02", "gpu03", "gpu07", "cpu1"]
+        );
+        assert_eq!(
+            expand_hostlist("rack[1-2]-node[8-9]"),
+            ["rack1-node8", "rack1-node9", "rack2-node8", "rack2-node9"]
+        );
+        assert_eq!(expand_hostlist(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn malformed_hostlists() {
+        // kept as they are rather than panicking or going missing
+        assert_eq!(expand_hostlist("a]b["), ["a]b["]);
+        assert_eq!(expand_hostlist("a]b,c"), ["a]b", "c"]);
+        assert_eq!(expand_hostlist("gpu[03-01]"), ["gpu03-01"]);
+        assert_eq!(expand_hostlist("gpu[x-1],cpu1"), ["gpux-1", "cpu1"]);
+        assert_eq!(expand_hostlist("gpu[01"), ["gpu[01"]);
+    }
+}
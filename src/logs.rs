@@ -0,0 +1,14 @@
+use crate::command::Runner;
+
+// enough to see what a job is up to, without copying
+// a whole log over SSH every couple of seconds
+pub const LOG_LINES: usize = 500;
+
+// The end of a job's output file, read with tail wherever the Slurm
+// commands run, so that it works the same over SSH.
+pub fn get_log(runner: &Runner, path: &str) -> Result<String, String> {
+    if path.is_empty() || path == "N/A" {
+        return Err("Slurm doesn't say where this job's output goes".to_string());
+    }
+    runner.run("tail", &["-n", &LOG_LINES.to_string(), path])
+}
@@ -1,20 +1,125 @@
 mod app;
+mod backend;
+mod cli;
+mod command;
+mod config;
+mod deps;
+mod form;
 mod jobs;
+mod logs;
+mod output;
+mod partitions;
+mod recording;
+mod sbatch;
+mod scancel;
+mod scontrol;
+mod simulator;
+mod slurmrestd;
+mod squeue_json;
+mod steps;
+mod templates;
+mod time;
 mod tui;
 mod ui;
+mod usage;
 
 use app::EditorState;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use regex::Regex;
 use std::{
     io::Result,
     time::{Duration, Instant},
 };
 
 use crate::app::{Action, App};
+use crate::backend::get_jobs_from;
+use crate::cli::{Command, Format};
+use crate::config::{Config, SlurmRestdConfig};
+use crate::jobs::{get_cluster_overview, matching_jobs};
 use crate::tui::Tui;
 
 fn main() -> Result<()> {
-    let app = App::new();
+    let args = cli::parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, cli::USAGE);
+        std::process::exit(2);
+    });
+    if let Command::Help = args.command {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid config: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(host) = args.host {
+        config.host = Some(host);
+        config.slurmrestd = None;
+        config.clusters.clear();
+    }
+    if let Some(url) = args.slurmrestd {
+        config.host = None;
+        config.clusters.clear();
+        match &mut config.slurmrestd {
+            Some(rest) => rest.url = url,
+            None => config.slurmrestd = Some(SlurmRestdConfig::new(url)),
+        }
+    }
+    let clusters = match &args.replay {
+        Some(dir) => backend::from_recording(dir),
+        None if args.simulate => Ok(backend::simulated(&config.simulator)),
+        None => backend::from_config(&config, args.record.as_deref()),
+    };
+    let clusters = clusters.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    // print what the clusters which answered know, but still fail if
+    // any of them did not
+    let get_jobs = |filter: &Regex| {
+        let (jobs, errors) = get_jobs_from(&clusters.iter().collect::<Vec<_>>(), false);
+        for e in &errors {
+            eprintln!("{}", e);
+        }
+        (matching_jobs(&jobs, filter), errors.is_empty())
+    };
+
+    match args.command {
+        Command::Tui | Command::Help => (),
+        Command::Print {
+            format,
+            fields,
+            filter,
+        } => {
+            let (jobs, ok) = get_jobs(&filter);
+            match output::format_jobs(&jobs, &fields, format) {
+                Ok(output) => print!("{}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+            if !ok {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Overview {
+            format,
+            group_by,
+            filter,
+        } => {
+            let (jobs, ok) = get_jobs(&filter);
+            let overview = get_cluster_overview(&jobs, group_by);
+            print!("{}", output::format_overview(&overview, format));
+            if !ok {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    }
+
+    let app = App::new(config, clusters);
     let tick_rate = Duration::from_millis(500);
     let mut tui = Tui::new();
     tui.enter();
@@ -58,6 +163,13 @@ fn handle_keys(key: KeyEvent, app: &App) -> Option<Action> {
             KeyCode::Tab => Some(Action::ToggleFocus),
             _ => Some(Action::InputKey(key)),
         },
+        EditorState::Form => match key.code {
+            KeyCode::Esc => Some(Action::ResetView),
+            KeyCode::Enter => Some(Action::SubmitForm),
+            KeyCode::Tab | KeyCode::Down => Some(Action::NextField),
+            KeyCode::BackTab | KeyCode::Up => Some(Action::PreviousField),
+            _ => Some(Action::FormInput(key)),
+        },
         EditorState::Normal => match key.code {
             KeyCode::Esc => Some(Action::ResetView),
             KeyCode::Up | KeyCode::Char('k') => Some(Action::Up),
@@ -78,6 +190,29 @@ fn handle_keys(key: KeyEvent, app: &App) -> Option<Action> {
             }
             KeyCode::Char('?') => Some(Action::ToggleHelp),
             KeyCode::Char('o') => Some(Action::ToggleOverview),
+            KeyCode::Char('b') => Some(Action::CycleGroupBy),
+            KeyCode::Char('c') => Some(Action::CycleCluster),
+            KeyCode::Char('f') => Some(Action::ToggleFinished),
+            KeyCode::Char('r') => Some(Action::ToggleReasons),
+            KeyCode::Char('p') => Some(Action::TogglePartitions),
+            KeyCode::Enter => Some(Action::Select),
+            KeyCode::Char(' ') => Some(Action::ToggleArray),
+            KeyCode::Char('s') => Some(Action::ToggleSteps),
+            KeyCode::Char('u') => Some(Action::ToggleUsage),
+            KeyCode::Char('d') => Some(Action::ToggleDependencies),
+            KeyCode::Char('i') => Some(Action::ToggleInfo),
+            KeyCode::Char('B') => Some(Action::ToggleScript),
+            KeyCode::Char('l') => Some(Action::ToggleStdout),
+            KeyCode::Char('L') => Some(Action::ToggleStderr),
+            KeyCode::Char('e') => Some(Action::EditJob),
+            KeyCode::Char('R') => Some(Action::ResubmitJob),
+            KeyCode::Char('K') => Some(Action::CancelJob),
+            KeyCode::Char('C') => Some(Action::CancelListedJobs),
+            KeyCode::Char('h') => Some(Action::HoldJob),
+            KeyCode::Char('H') => Some(Action::ReleaseJob),
+            KeyCode::Char('n') => Some(Action::ToggleTemplates),
+            KeyCode::Char('x') => Some(Action::Export(Format::Csv)),
+            KeyCode::Char('X') => Some(Action::Export(Format::Json)),
             KeyCode::Tab => Some(Action::ToggleFocus),
             _ => None,
         },
@@ -0,0 +1,171 @@
+use serde_json::{json, Map, Value};
+
+use crate::cli::Format;
+use crate::jobs::{ClusterOverview, Job};
+
+// Jobs as JSON, CSV, Markdown or an aligned table, with one object/row per job.
+// Fields are named as in Job::field_names(). If `fields` is empty, all
+// of them are included.
+pub fn format_jobs(jobs: &[Job], fields: &[String], format: Format) -> Result<String, String> {
+    let names = Job::field_names();
+    let columns: Vec<usize> = match fields {
+        [] => (0..names.len()).collect(),
+        _ => fields
+            .iter()
+            .map(|f| {
+                names.iter().position(|n| n == f).ok_or(format!(
+                    "unknown field {}, expected one of {}",
+                    f,
+                    names.join(",")
+                ))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    let header: Vec<String> = columns.iter().map(|&c| names[c].to_string()).collect();
+    let rows: Vec<Vec<String>> = jobs
+        .iter()
+        .map(|job| {
+            let values = job.field_values();
+            columns.iter().map(|&c| values[c].clone()).collect()
+        })
+        .collect();
+    Ok(format_rows(&header, &rows, format))
+}
+
+pub fn format_rows(header: &[String], rows: &[Vec<String>], format: Format) -> String {
+    match format {
+        Format::Json => to_json(header, rows),
+        Format::Csv => to_csv(header, rows),
+        Format::Table => to_table(header, rows),
+        Format::Markdown => to_markdown(header, rows),
+    }
+}
+
+// The overview table as shown in the TUI, with the totals first. In
+// JSON, counts are numbers rather than strings.
+pub fn format_overview(overview: &ClusterOverview, format: Format) -> String {
+    let label = overview.group_by.label();
+    let total_gpus: u32 = overview.group_stats.iter().map(|s| s.gpus_used).sum();
+    if format == Format::Json {
+        let groups: Vec<Value> = overview
+            .group_stats
+            .iter()
+            .map(|s| {
+                json!({
+                    "name": s.name,
+                    "running": s.running_jobs,
+                    "pending": s.pending_jobs,
+                    "gpus": s.gpus_used,
+                })
+            })
+            .collect();
+        let report = json!({
+            "group_by": label,
+            "running": overview.jobs_running,
+            "pending": overview.jobs_pending,
+            "completing": overview.jobs_completing,
+            "gpus": total_gpus,
+            "groups": groups,
+        });
+        return serde_json::to_string_pretty(&report).unwrap() + "\n";
+    }
+
+    let header: Vec<String> = [label, "Running", "Pending", "GPUs"]
+        .map(String::from)
+        .to_vec();
+    let total = vec![
+        "TOTAL".to_string(),
+        overview.jobs_running.to_string(),
+        overview.jobs_pending.to_string(),
+        total_gpus.to_string(),
+    ];
+    let rows: Vec<Vec<String>> = std::iter::once(total)
+        .chain(overview.group_stats.iter().map(|s| {
+            vec![
+                s.name.clone(),
+                s.running_jobs.to_string(),
+                s.pending_jobs.to_string(),
+                s.gpus_used.to_string(),
+            ]
+        }))
+        .collect();
+    let table = format_rows(&header, &rows, format);
+    let title = format!(
+        "Overview by {} ({} running, {} pending, {} completing)",
+        label, overview.jobs_running, overview.jobs_pending, overview.jobs_completing
+    );
+    match format {
+        Format::Table => format!("{}\n\n{}", title, table),
+        Format::Markdown => format!("**{}**\n\n{}", title, table),
+        _ => table,
+    }
+}
+
+fn to_json(header: &[String], rows: &[Vec<String>]) -> String {
+    let objects: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let object: Map<String, Value> = header
+                .iter()
+                .zip(row)
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect();
+            Value::Object(object)
+        })
+        .collect();
+    serde_json::to_string_pretty(&objects).unwrap() + "\n"
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(header: &[String], rows: &[Vec<String>]) -> String {
+    std::iter::once(header)
+        .chain(rows.iter().map(|r| r.as_slice()))
+        .map(|row| {
+            let fields: Vec<String> = row.iter().map(|v| csv_field(v)).collect();
+            fields.join(",") + "\n"
+        })
+        .collect()
+}
+
+fn to_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    std::iter::once(header)
+        .chain(rows.iter().map(|r| r.as_slice()))
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(v, w)| format!("{:<w$}", v, w = w))
+                .collect();
+            cells.join("  ").trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+fn to_markdown(header: &[String], rows: &[Vec<String>]) -> String {
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row.iter().map(|v| markdown_cell(v)).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let separator = vec!["---".to_string(); header.len()];
+    std::iter::once(line(header))
+        .chain(std::iter::once(line(&separator)))
+        .chain(rows.iter().map(|r| line(r)))
+        .collect()
+}
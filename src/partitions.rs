@@ -0,0 +1,94 @@
+use crate::command::Runner;
+
+// How many nodes (or CPUs) of a partition are allocated, idle, or
+// neither (down, drained...), as in sinfo's A/I/O/T
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+    pub allocated: u32,
+    pub idle: u32,
+    pub other: u32,
+    pub total: u32,
+}
+
+impl Counts {
+    // e.g. 3/1/0/4
+    fn parse(counts: &str) -> Option<Self> {
+        let counts: Vec<u32> = counts
+            .split('/')
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        match counts[..] {
+            [allocated, idle, other, total] => Some(Counts {
+                allocated,
+                idle,
+                other,
+                total,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct Partition {
+    // empty when only the one cluster is set up
+    pub cluster: String,
+    // the default partition is marked with a *, as sinfo does
+    pub name: String,
+    // up, down, drain or inactive
+    pub state: String,
+    pub nodes: Counts,
+    pub cpus: Counts,
+}
+
+pub fn get_partitions(runner: &Runner) -> Result<Vec<Partition>, String> {
+    let output = runner.run("sinfo", &["--noheader", "--format", "%P|%a|%F|%C"])?;
+    let partitions = output
+        .lines()
+        .filter_map(|l| {
+            let parts: Vec<&str> = l.split('|').collect();
+            match parts[..] {
+                [name, state, nodes, cpus] => Some(Partition {
+                    cluster: String::new(),
+                    name: name.to_string(),
+                    state: state.to_string(),
+                    nodes: Counts::parse(nodes)?,
+                    cpus: Counts::parse(cpus)?,
+                }),
+                // e.g. the CLUSTER: line printed with --clusters
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(partitions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, Replay};
+    use std::sync::Arc;
+
+    #[test]
+    fn sinfo() {
+        let output = "CLUSTER: hpc\ngpu*|up|3/1/0/4|72/24/0/96\ndebug|down|0/0/2/2|0/0/48/48\n";
+        let args = ["sinfo", "--noheader", "--format", "%P|%a|%F|%C"];
+        let runner = Runner {
+            recording: Some(Arc::new(Recording::Replay(Replay::new(&[(&args, output)])))),
+            ..Runner::default()
+        };
+        let partitions = get_partitions(&runner).unwrap();
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].name, "gpu*");
+        assert_eq!(partitions[0].state, "up");
+        assert_eq!(
+            partitions[0].nodes,
+            Counts {
+                allocated: 3,
+                idle: 1,
+                other: 0,
+                total: 4
+            }
+        );
+        assert_eq!(partitions[1].cpus.other, 48);
+    }
+}
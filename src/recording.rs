@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde_json::{json, Value};
+
+// `lazyslurm --record DIR` saves every Slurm command it runs, and what
+// came back, to DIR/recording.jsonl with one JSON object per line:
+//
+//     {"time": "2026-01-01T12:00:00+00:00", "cluster": "",
+//      "command": ["squeue", "--array", ...], "output": "..."}
+//
+// with "error" instead of "output" when the command failed. Then
+// `lazyslurm --replay DIR` plays it back without needing Slurm at all,
+// e.g. to reproduce a bug report or to work on lazyslurm elsewhere.
+const RECORDING_FILE: &str = "recording.jsonl";
+
+pub enum Recording {
+    Record(Recorder),
+    Replay(Replay),
+}
+
+// One file shared by every cluster's commands
+#[derive(Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+    cluster: String,
+}
+
+impl Recorder {
+    pub fn create(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        let path = dir.join(RECORDING_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+        Ok(Recorder {
+            file: Arc::new(Mutex::new(file)),
+            cluster: String::new(),
+        })
+    }
+
+    pub fn for_cluster(&self, cluster: &str) -> Self {
+        Recorder {
+            file: self.file.clone(),
+            cluster: cluster.to_string(),
+        }
+    }
+
+    // A recording which can't be written shouldn't get in the way of
+    // using lazyslurm, so errors here are ignored.
+    pub fn record(&self, program: &str, args: &[&str], result: &Result<String, String>) {
+        let command: Vec<&str> = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect();
+        let mut entry = json!({
+            "time": Local::now().to_rfc3339(),
+            "cluster": self.cluster,
+            "command": command,
+        });
+        match result {
+            Ok(output) => entry["output"] = json!(output),
+            Err(e) => entry["error"] = json!(e),
+        }
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{}", entry);
+        }
+    }
+}
+
+// For each command, what it gave and how long into the recording
+type Outputs = HashMap<Vec<String>, Vec<(Duration, Result<String, String>)>>;
+
+// The recording is played back in its own time, from when the replay
+// starts: each command gives back what it last gave by the same time
+// into the recording (or what it first gave, if it hadn't been run by
+// then). So the jobs change as they did then, however often squeue is
+// run, and once the recording ends they stay as they were at the end.
+pub struct Replay {
+    outputs: Outputs,
+    started: Instant,
+}
+
+impl Replay {
+    pub fn run(&self, program: &str, args: &[&str]) -> Result<String, String> {
+        self.run_at(self.started.elapsed(), program, args)
+    }
+
+    fn run_at(&self, elapsed: Duration, program: &str, args: &[&str]) -> Result<String, String> {
+        let command: Vec<String> = std::iter::once(program)
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect();
+        let Some(outputs) = self.outputs.get(&command) else {
+            return Err(format!("{} is not in the recording", command.join(" ")));
+        };
+        let ran = outputs.partition_point(|(time, _)| *time <= elapsed);
+        outputs[ran.saturating_sub(1)].1.clone()
+    }
+}
+
+#[cfg(test)]
+impl Replay {
+    // A replay which answers each command with the given output
+    pub fn new(outputs: &[(&[&str], &str)]) -> Self {
+        let outputs = outputs
+            .iter()
+            .map(|(command, output)| {
+                let command = command.iter().map(|arg| arg.to_string()).collect();
+                (command, vec![(Duration::ZERO, Ok(output.to_string()))])
+            })
+            .collect();
+        Replay {
+            outputs,
+            started: Instant::now(),
+        }
+    }
+}
+
+// The recorded clusters, in the order they first appear, with what was
+// recorded for each.
+pub fn load_replay(dir: &Path) -> Result<Vec<(String, Replay)>, String> {
+    let path = dir.join(RECORDING_FILE);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+    let mut clusters: Vec<(String, Outputs)> = vec![];
+    // when the recording started, as the clusters are played back together
+    let mut start: Option<DateTime<FixedOffset>> = None;
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |e: &str| format!("{} line {}: {}", path.display(), i + 1, e);
+        let entry: Value = serde_json::from_str(line).map_err(|e| invalid(&e.to_string()))?;
+        let time = entry["time"]
+            .as_str()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .ok_or_else(|| invalid("time should be an RFC 3339 timestamp"))?;
+        let start = *start.get_or_insert(time);
+        // entries are written as commands finish, so they can be
+        // slightly out of order when several run at once
+        let time = (time - start).to_std().unwrap_or_default();
+        let cluster = entry["cluster"].as_str().unwrap_or_default().to_string();
+        let command: Vec<String> = entry["command"]
+            .as_array()
+            .ok_or_else(|| invalid("no command"))?
+            .iter()
+            .map(|arg| arg.as_str().map(String::from))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("command should be a list of strings"))?;
+        let result = match (entry["output"].as_str(), entry["error"].as_str()) {
+            (Some(output), _) => Ok(output.to_string()),
+            (None, Some(e)) => Err(e.to_string()),
+            (None, None) => return Err(invalid("no output or error")),
+        };
+
+        let outputs = match clusters.iter().position(|(name, _)| *name == cluster) {
+            Some(c) => &mut clusters[c].1,
+            None => {
+                clusters.push((cluster, HashMap::new()));
+                &mut clusters.last_mut().unwrap().1
+            }
+        };
+        outputs.entry(command).or_default().push((time, result));
+    }
+    if clusters.is_empty() {
+        return Err(format!("{} is empty", path.display()));
+    }
+    let started = Instant::now();
+    Ok(clusters
+        .into_iter()
+        .map(|(name, mut outputs)| {
+            for results in outputs.values_mut() {
+                results.sort_by_key(|(time, _)| *time);
+            }
+            (name, Replay { outputs, started })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A fresh directory for each test, as they run in parallel
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("lazyslurm-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn load_lines(name: &str, lines: &[&str]) -> Result<Vec<(String, Replay)>, String> {
+        let dir = test_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(RECORDING_FILE), lines.join("\n")).unwrap();
+        load_replay(&dir)
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let dir = test_dir("record");
+        let recorder = Recorder::create(&dir).unwrap();
+        let alpha = recorder.for_cluster("alpha");
+        let beta = recorder.for_cluster("beta");
+        alpha.record("squeue", &["--array"], &Ok("first\n".to_string()));
+        beta.record("squeue", &["--array"], &Ok("beta\n".to_string()));
+        alpha.record("sacct", &["--jobs", "1"], &Err("sacct failed".to_string()));
+        alpha.record("squeue", &["--array"], &Ok("second\n".to_string()));
+
+        let replay = load_replay(&dir).unwrap();
+        let names: Vec<&str> = replay.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        let alpha = &replay[0].1;
+        // however often squeue runs, it gives what it gave at the time
+        for _ in 0..3 {
+            let output = alpha.run_at(Duration::ZERO, "squeue", &["--array"]);
+            assert_eq!(output, Ok("first\n".to_string()));
+        }
+        let later = Duration::from_secs(3600);
+        assert_eq!(
+            alpha.run_at(later, "squeue", &["--array"]),
+            Ok("second\n".to_string())
+        );
+        assert_eq!(
+            alpha.run_at(later, "sacct", &["--jobs", "1"]),
+            Err("sacct failed".to_string())
+        );
+        assert_eq!(
+            alpha.run_at(later, "sacct", &["--jobs", "2"]),
+            Err("sacct --jobs 2 is not in the recording".to_string())
+        );
+        assert_eq!(
+            replay[1].1.run_at(later, "squeue", &["--array"]),
+            Ok("beta\n".to_string())
+        );
+    }
+
+    #[test]
+    fn replay_by_time() {
+        let entry = |time: &str, output: &str| {
+            format!(
+                r#"{{"time": "2026-01-01T12:{}+00:00", "cluster": "", "command": ["squeue"], "output": "{}"}}"#,
+                time, output
+            )
+        };
+        let lines = [
+            entry("00:00", "a"),
+            entry("00:10", "b"),
+            entry("00:30", "c"),
+        ];
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let replay = &load_lines("time", &lines).unwrap()[0].1;
+        let at = |secs| {
+            replay
+                .run_at(Duration::from_secs(secs), "squeue", &[])
+                .unwrap()
+        };
+        assert_eq!(
+            [at(0), at(9), at(10), at(29), at(300)],
+            ["a", "a", "b", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn invalid_recordings() {
+        let error = |lines: &[&str]| load_lines("invalid", lines).err().unwrap();
+        let time = r#""time": "2026-01-01T12:00:00+00:00""#;
+
+        assert!(load_replay(&test_dir("missing"))
+            .err()
+            .unwrap()
+            .starts_with("could not read"));
+        assert!(error(&[""]).ends_with("recording.jsonl is empty"));
+        assert!(error(&["not json"]).contains("recording.jsonl line 1: "));
+        let no_command = format!(r#"{{{}, "output": ""}}"#, time);
+        assert!(error(&["", &no_command]).ends_with("line 2: no command"));
+        let not_strings = format!(r#"{{{}, "command": ["squeue", 1], "output": ""}}"#, time);
+        assert!(error(&[&not_strings]).ends_with("command should be a list of strings"));
+        let no_output = format!(r#"{{{}, "command": ["squeue"]}}"#, time);
+        assert!(error(&[&no_output]).ends_with("no output or error"));
+        let no_time = r#"{"command": ["squeue"], "output": ""}"#;
+        assert!(error(&[no_time]).ends_with("time should be an RFC 3339 timestamp"));
+    }
+}
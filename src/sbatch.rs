@@ -0,0 +1,28 @@
+use crate::command::Runner;
+
+// Submit a batch script with some extra sbatch options, given as long
+// option names and values, e.g. ("--time", "2:00:00"). Options given
+// on the command line take precedence over #SBATCH lines in the script.
+// Options left empty are not passed at all. Returns the new job's ID.
+pub fn submit(
+    runner: &Runner,
+    script: &str,
+    options: &[(String, String)],
+) -> Result<String, String> {
+    let mut args = vec!["--parsable".to_string()];
+    args.extend(
+        options
+            .iter()
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(k, v)| format!("{}={}", k, v)),
+    );
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = runner.run_with_input("sbatch", &args, script)?;
+    // --parsable prints jobid[;cluster]
+    Ok(output
+        .trim()
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
@@ -0,0 +1,17 @@
+use crate::command::Runner;
+
+// Cancel a job, or every task of an array when given the array's ID
+pub fn cancel_job(runner: &Runner, job_id: &str) -> Result<(), String> {
+    runner.run("scancel", &[job_id])?;
+    Ok(())
+}
+
+// Who the commands run as, i.e. whose jobs can be cancelled. Over SSH
+// this is the user on the login node, which needn't be the local one.
+pub fn get_user(runner: &Runner) -> Result<String, String> {
+    let user = runner.run("id", &["-un"])?;
+    match user.trim() {
+        "" => Err("id did not say who you are".to_string()),
+        user => Ok(user.to_string()),
+    }
+}
@@ -0,0 +1,129 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::command::Runner;
+
+// Keys look like Key=, but can contain a few extra characters,
+// e.g. Socks/Node= or NtasksPerN:B:S:C=
+static KEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\s)([A-Za-z_][\w/:]*)=").unwrap());
+
+// Everything scontrol knows about a job, as key=value pairs in the
+// order it prints them. Values can contain spaces (e.g. Command), so
+// a value runs until the next thing which looks like a key. Lines
+// which are not key=value pairs are kept whole, with an empty key.
+pub fn show_job(runner: &Runner, job_id: &str) -> Result<Vec<(String, String)>, String> {
+    let output = runner.run("scontrol", &["show", "job", "-dd", job_id])?;
+    Ok(output.lines().flat_map(parse_pairs).collect())
+}
+
+fn parse_pairs(line: &str) -> Vec<(String, String)> {
+    let line = line.trim();
+    let keys: Vec<_> = KEY_RE
+        .captures_iter(line)
+        .map(|c| c.get(1).unwrap())
+        .collect();
+    if keys.is_empty() || keys[0].start() != 0 {
+        return match line {
+            "" => vec![],
+            _ => vec![(String::new(), line.to_string())],
+        };
+    }
+    keys.iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let value_end = keys.get(i + 1).map_or(line.len(), |next| next.start());
+            let value = &line[key.end() + 1..value_end];
+            (key.as_str().to_string(), value.trim().to_string())
+        })
+        .collect()
+}
+
+// The batch script a job was submitted with. scontrol can only get it
+// for jobs which are still around, after that it has to come from the
+// accounting database (if the cluster is set up to store scripts).
+pub fn get_batch_script(runner: &Runner, job_id: &str) -> Result<String, String> {
+    let scontrol_error = match runner.run("scontrol", &["write", "batch_script", job_id, "-"]) {
+        Ok(script) => return Ok(script),
+        Err(e) => e,
+    };
+    let output = runner
+        .run("sacct", &["--batch-script", "--jobs", job_id])
+        .map_err(|e| format!("{}; {}", scontrol_error, e))?;
+    // sacct puts a header above the script:
+    // Batch Script for 1234
+    // ----------------------
+    let script: Vec<&str> = output
+        .lines()
+        .skip_while(|l| !l.starts_with("---"))
+        .skip(1)
+        .collect();
+    if script.iter().all(|l| l.trim().is_empty()) {
+        return Err(format!(
+            "{}; sacct has no batch script for job {} either",
+            scontrol_error, job_id
+        ));
+    }
+    Ok(script.join("\n"))
+}
+
+// Change some of a job's parameters, given as scontrol keys and values,
+// e.g. TimeLimit=2:00:00. Most of these can only be changed while the
+// job is pending.
+pub fn update_job(
+    runner: &Runner,
+    job_id: &str,
+    changes: &[(String, String)],
+) -> Result<(), String> {
+    let mut args = vec!["update".to_string(), format!("JobId={}", job_id)];
+    args.extend(changes.iter().map(|(k, v)| format!("{}={}", k, v)));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    runner.run("scontrol", &args)?;
+    Ok(())
+}
+
+// Holding a pending job keeps it from starting until it is released.
+// Given an array's ID, these apply to all of its pending tasks.
+pub fn hold_job(runner: &Runner, job_id: &str) -> Result<(), String> {
+    runner.run("scontrol", &["hold", job_id])?;
+    Ok(())
+}
+
+pub fn release_job(runner: &Runner, job_id: &str) -> Result<(), String> {
+    runner.run("scontrol", &["release", job_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Recording, Replay};
+    use std::sync::Arc;
+
+    // scontrol has forgotten the job, so the script comes from sacct
+    fn runner(sacct: &str) -> Runner {
+        let replay = Replay::new(&[(&["sacct", "--batch-script", "--jobs", "1234"], sacct)]);
+        Runner {
+            recording: Some(Arc::new(Recording::Replay(replay))),
+            ..Runner::default()
+        }
+    }
+
+    #[test]
+    fn batch_script_from_sacct() {
+        let output =
+            "Batch Script for 1234\n----------------------\n#!/bin/bash\npython train.py\n";
+        let script = get_batch_script(&runner(output), "1234").unwrap();
+        assert_eq!(script, "#!/bin/bash\npython train.py");
+    }
+
+    #[test]
+    fn no_batch_script() {
+        for output in ["", "Batch Script for 1234\n----------------------\n\n"] {
+            let error = get_batch_script(&runner(output), "1234").unwrap_err();
+            // both what scontrol and what sacct said
+            assert!(error.contains("scontrol write batch_script 1234 -"));
+            assert!(error.contains("sacct has no batch script for job 1234"));
+        }
+    }
+}
@@ -0,0 +1,1083 @@
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::backend::Backend;
+use crate::config::SimulatorConfig;
+use crate::jobs::{compact_state, is_finished, parse_paths, Job};
+use crate::logs::LOG_LINES;
+use crate::partitions::{Counts, Partition};
+use crate::steps::Step;
+use crate::time::{format_duration, parse_duration};
+use crate::usage::{format_mem, Efficiency, StepUsage};
+
+// `lazyslurm --simulate` shows a made up cluster instead of a real one,
+// for demos and for trying things out without going near Slurm. Made
+// up users keep submitting jobs, which wait for room on a node, run for
+// a few minutes and then complete or fail. Jobs can be edited and
+// submitted as usual. The cluster is set up in [simulator] in the
+// config file.
+
+// squeue keeps listing jobs for a while after they end (MinJobAge)
+const MIN_JOB_AGE: TimeDelta = TimeDelta::minutes(5);
+const CPUS_PER_NODE: u32 = 64;
+const MAX_ARRAY_TASKS: u32 = 1000;
+
+const JOB_NAMES: [&str; 10] = [
+    "train",
+    "eval",
+    "preprocess",
+    "sweep",
+    "finetune",
+    "inference",
+    "render",
+    "align",
+    "benchmark",
+    "simulate",
+];
+
+// xorshift64*, plenty for making up jobs
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    fn seconds(&mut self, from: i64, to: i64) -> TimeDelta {
+        TimeDelta::seconds(from + self.below((to - from).max(1) as u64) as i64)
+    }
+}
+
+struct Node {
+    name: String,
+    partition: String,
+    free_gpus: u32,
+    free_cpus: u32,
+}
+
+#[derive(Clone)]
+struct SimJob {
+    id: u32,
+    // array job id and task id
+    array: Option<(u32, u32)>,
+    name: String,
+    user: String,
+    account: String,
+    partition: String,
+    qos: String,
+    nice: i64,
+    gpus: u32,
+    cpus: u32,
+    num_tasks: u32,
+    time_limit: TimeDelta,
+    req_nodes: String,
+    exc_nodes: String,
+    work_dir: String,
+    script: String,
+    submit_time: NaiveDateTime,
+    // decided up front: how long it runs for, how it ends and how
+    // much of its CPUs and memory it uses
+    run_time: TimeDelta,
+    outcome: &'static str,
+    usage: f64,
+    state: &'static str,
+    reason: &'static str,
+    // held jobs stay pending until they are released
+    held: bool,
+    node: Option<usize>,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+}
+
+impl SimJob {
+    fn job_id(&self) -> String {
+        match self.array {
+            Some((array_id, task)) => format!("{}_{}", array_id, task),
+            None => self.id.to_string(),
+        }
+    }
+
+    // a whole array can be given by its id
+    fn matches(&self, job_id: &str) -> bool {
+        self.job_id() == job_id
+            || self.id.to_string() == job_id
+            || self
+                .array
+                .is_some_and(|(array_id, _)| array_id.to_string() == job_id)
+    }
+
+    fn elapsed(&self, now: NaiveDateTime) -> TimeDelta {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => end - start,
+            (Some(start), None) => now - start,
+            _ => TimeDelta::zero(),
+        }
+    }
+
+    fn mem(&self) -> u64 {
+        self.cpus as u64 * (4 << 30)
+    }
+
+    fn tres(&self) -> String {
+        let mut tres = format!(
+            "cpu={},mem={}G,node=1,billing={}",
+            self.cpus,
+            self.mem() >> 30,
+            self.cpus
+        );
+        if self.gpus > 0 {
+            tres.push_str(&format!(",gres/gpu={}", self.gpus));
+        }
+        tres
+    }
+
+    // as in Slurm, holding a job sets its priority to 0
+    fn priority(&self) -> i64 {
+        match self.held {
+            true => 0,
+            false => (10_000 - self.nice).max(1),
+        }
+    }
+
+    fn to_job(&self, now: NaiveDateTime, nodes: &[Node], gpu_type: &str) -> Job {
+        let tres = match self.gpus {
+            0 => self.tres(),
+            gpus => format!("{},gres/gpu:{}={}", self.tres(), gpu_type, gpus),
+        };
+        let mut job = Job {
+            StateCompact: compact_state(self.state).to_string(),
+            State: self.state.to_string(),
+            Reason: self.reason.to_string(),
+            Name: self.name.clone(),
+            UserName: self.user.clone(),
+            Account: self.account.clone(),
+            QOS: self.qos.clone(),
+            Cluster: String::new(),
+            JobID: self.job_id(),
+            ArrayJobID: self
+                .array
+                .map_or(self.id, |(array_id, _)| array_id)
+                .to_string(),
+            ArrayTaskID: self
+                .array
+                .map_or("N/A".to_string(), |(_, task)| task.to_string()),
+            Partition: self.partition.clone(),
+            NodeList: self.node.map_or(String::new(), |n| nodes[n].name.clone()),
+            ReqNodes: self.req_nodes.clone(),
+            ExcNodes: self.exc_nodes.clone(),
+            SubmitTime: timestamp(Some(self.submit_time)),
+            StartTime: timestamp(self.start),
+            TimeLimit: format_duration(self.time_limit),
+            TimeUsed: format_duration(self.elapsed(now)),
+            TRES: tres,
+            Dependency: String::new(),
+            NumTasks: self.num_tasks.to_string(),
+            Priority: self.priority().to_string(),
+            Nice: self.nice.to_string(),
+            WorkDir: self.work_dir.clone(),
+            Command: format!("{}/run.sh", self.work_dir),
+            STDOUT: format!("{}/slurm-%j.out", self.work_dir),
+            STDERR: format!("{}/slurm-%j.out", self.work_dir),
+        };
+        parse_paths(&mut job);
+        job
+    }
+}
+
+fn timestamp(time: Option<NaiveDateTime>) -> String {
+    time.map_or("N/A".to_string(), |t| {
+        t.format("%Y-%m-%dT%H:%M:%S").to_string()
+    })
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+struct State {
+    config: SimulatorConfig,
+    rng: Rng,
+    nodes: Vec<Node>,
+    jobs: Vec<SimJob>,
+    next_id: u32,
+}
+
+impl State {
+    // an empty cluster, with its nodes shared out between the partitions
+    fn new(config: &SimulatorConfig) -> Self {
+        let seed = config
+            .seed
+            .unwrap_or(Local::now().timestamp_nanos_opt().unwrap_or(0) as u64);
+        let nodes = (0..config.nodes as usize)
+            .map(|i| Node {
+                name: format!("node{:02}", i + 1),
+                partition: config.partitions[i % config.partitions.len()].clone(),
+                free_gpus: config.gpus_per_node,
+                free_cpus: CPUS_PER_NODE,
+            })
+            .collect();
+        State {
+            config: config.clone(),
+            rng: Rng(seed.max(1)),
+            nodes,
+            jobs: vec![],
+            next_id: 1000,
+        }
+    }
+
+    // Everything about a job except its name, user and resources,
+    // which are made up or come from sbatch options.
+    fn new_job(&mut self, user: &str, now: NaiveDateTime) -> SimJob {
+        let outcome = match self.rng.below(100) {
+            0..=74 => "COMPLETED",
+            75..=92 => "FAILED",
+            _ => "OUT_OF_MEMORY",
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        SimJob {
+            id,
+            array: None,
+            name: String::new(),
+            user: user.to_string(),
+            account: "research".to_string(),
+            partition: self.config.partitions[0].clone(),
+            qos: "normal".to_string(),
+            nice: 0,
+            gpus: 0,
+            cpus: 1,
+            num_tasks: 1,
+            time_limit: TimeDelta::hours(1),
+            req_nodes: String::new(),
+            exc_nodes: String::new(),
+            work_dir: format!("/home/{}", user),
+            script: String::new(),
+            submit_time: now,
+            run_time: self.rng.seconds(20, 600),
+            outcome,
+            usage: 0.1 + self.rng.below(90) as f64 / 100.0,
+            state: "PENDING",
+            reason: "Priority",
+            held: false,
+            node: None,
+            start: None,
+            end: None,
+        }
+    }
+
+    // One of the made up users submits a job, or a job array
+    fn submit_random(&mut self, now: NaiveDateTime) {
+        let user = self.rng.pick(&self.config.users).clone();
+        let mut job = self.new_job(&user, now);
+        job.name = match self.rng.chance(50) {
+            true => format!("{}-{}", self.rng.pick(&JOB_NAMES), self.rng.below(100)),
+            false => self.rng.pick(&JOB_NAMES).to_string(),
+        };
+        job.partition = self.rng.pick(&self.config.partitions).clone();
+        job.gpus = match self.config.gpus_per_node {
+            0 => 0,
+            max => (*self.rng.pick(&[0, 1, 1, 2, 4, 8])).min(max),
+        };
+        job.cpus = (job.gpus * 8).max(*self.rng.pick(&[1, 2, 4, 8]));
+        job.time_limit = *self.rng.pick(&[
+            TimeDelta::minutes(30),
+            TimeDelta::hours(1),
+            TimeDelta::hours(4),
+            TimeDelta::hours(12),
+            TimeDelta::days(1),
+        ]);
+        job.work_dir = format!("/home/{}/{}", user, job.name);
+        job.script = format!(
+            "#!/bin/bash\n\
+             #SBATCH --job-name={}\n\
+             #SBATCH --partition={}\n\
+             #SBATCH --time={}\n\
+             #SBATCH --cpus-per-task={}\n\
+             #SBATCH --gres=gpu:{}\n\
+             \n\
+             python {}.py\n",
+            job.name,
+            job.partition,
+            format_duration(job.time_limit),
+            job.cpus,
+            job.gpus,
+            job.name
+        );
+        let tasks = match self.rng.chance(15) {
+            true => 2 + self.rng.below(7) as u32,
+            false => 1,
+        };
+        self.push(job, (tasks > 1).then(|| (0..tasks).collect()));
+    }
+
+    // Add a job, or one per task of an array, each with its own id as
+    // in Slurm.
+    fn push(&mut self, job: SimJob, tasks: Option<Vec<u32>>) {
+        let Some(tasks) = tasks else {
+            self.jobs.push(job);
+            return;
+        };
+        let array_id = job.id;
+        for (i, task) in tasks.into_iter().enumerate() {
+            let id = match i {
+                0 => array_id,
+                _ => {
+                    self.next_id += 1;
+                    self.next_id - 1
+                }
+            };
+            let mut task_job = job.clone();
+            task_job.id = id;
+            task_job.array = Some((array_id, task));
+            task_job.run_time = self.rng.seconds(20, 600);
+            if self.rng.chance(10) {
+                task_job.outcome = "FAILED";
+            }
+            self.jobs.push(task_job);
+        }
+    }
+
+    fn free(&mut self, i: usize) {
+        let job = &self.jobs[i];
+        if let Some(n) = job.node {
+            self.nodes[n].free_gpus += job.gpus;
+            self.nodes[n].free_cpus += job.cpus;
+        }
+    }
+
+    // Move time on to now: jobs finish, new ones are submitted and
+    // pending jobs start where they fit.
+    fn step(&mut self, now: NaiveDateTime) {
+        for i in 0..self.jobs.len() {
+            let job = &self.jobs[i];
+            let Some(start) = job.start else {
+                continue;
+            };
+            if job.end.is_none() && now >= start + job.run_time {
+                self.free(i);
+                let job = &mut self.jobs[i];
+                job.end = Some(start + job.run_time);
+                job.state = job.outcome;
+                job.reason = match job.outcome {
+                    "FAILED" => "NonZeroExitCode",
+                    "OUT_OF_MEMORY" => "OutOfMemory",
+                    _ => "None",
+                };
+            }
+        }
+        self.jobs
+            .retain(|j| j.end.is_none_or(|end| now - end < TimeDelta::days(1)));
+
+        let active = self.jobs.iter().filter(|j| j.end.is_none()).count();
+        if active < self.config.jobs as usize && self.rng.chance(30) {
+            self.submit_random(now);
+        }
+        self.schedule(now);
+    }
+
+    // Highest priority first, then first come first served, on the
+    // first node with room. The first job which doesn't fit waits for
+    // resources and the ones behind it for their turn.
+    fn schedule(&mut self, now: NaiveDateTime) {
+        let mut pending: Vec<usize> = (0..self.jobs.len())
+            .filter(|&i| self.jobs[i].state == "PENDING" && !self.jobs[i].held)
+            .collect();
+        pending.sort_by_key(|&i| (-self.jobs[i].priority(), self.jobs[i].id));
+
+        let mut blocked = false;
+        for i in pending {
+            let job = &self.jobs[i];
+            let node = self.nodes.iter().position(|n| {
+                n.partition == job.partition
+                    && n.free_gpus >= job.gpus
+                    && n.free_cpus >= job.cpus
+                    && (job.req_nodes.is_empty() || job.req_nodes.split(',').any(|r| r == n.name))
+                    && !job.exc_nodes.split(',').any(|x| x == n.name)
+            });
+            let job = &mut self.jobs[i];
+            match node {
+                Some(n) => {
+                    self.nodes[n].free_gpus -= job.gpus;
+                    self.nodes[n].free_cpus -= job.cpus;
+                    job.node = Some(n);
+                    job.start = Some(now);
+                    job.state = "RUNNING";
+                    job.reason = "None";
+                }
+                None if !blocked => {
+                    job.reason = "Resources";
+                    blocked = true;
+                }
+                None => job.reason = "Priority",
+            }
+        }
+    }
+
+    // The jobs (or tasks of an array) with this ID which haven't ended,
+    // or an error like scontrol's if there are none
+    fn active(&self, job_id: &str) -> Result<Vec<usize>, String> {
+        let jobs: Vec<usize> = (0..self.jobs.len())
+            .filter(|&i| self.jobs[i].matches(job_id) && self.jobs[i].end.is_none())
+            .collect();
+        match jobs.is_empty() {
+            true => Err(format!("Invalid job id specified: {}", job_id)),
+            false => Ok(jobs),
+        }
+    }
+
+    fn find(&self, job_id: &str) -> Result<&SimJob, String> {
+        self.jobs
+            .iter()
+            .find(|j| j.job_id() == job_id)
+            .ok_or(format!("Invalid job id specified: {}", job_id))
+    }
+}
+
+pub struct Simulator {
+    state: Mutex<State>,
+}
+
+impl Simulator {
+    pub fn new(config: &SimulatorConfig) -> Self {
+        let mut state = State::new(config);
+
+        // start part way through a day: some jobs finished a while ago,
+        // and the ones running now started at various times, some long
+        // enough ago to be close to their time limit
+        let now = now();
+        for _ in 0..config.jobs * 3 / 2 {
+            let submitted = now - state.rng.seconds(600, 4 * 3600);
+            state.submit_random(submitted);
+        }
+        let finished = state.jobs.len() / 3;
+        for job in &mut state.jobs[..finished] {
+            let end = now - TimeDelta::seconds(state.rng.below(12 * 3600) as i64);
+            job.start = Some(end - job.run_time);
+            job.submit_time = job.submit_time.min(end - job.run_time);
+            job.end = Some(end);
+            job.state = job.outcome;
+            job.reason = "None";
+        }
+        state.schedule(now);
+        for job in state.jobs.iter_mut().filter(|j| j.state == "RUNNING") {
+            let elapsed = state.rng.seconds(0, job.time_limit.num_seconds());
+            job.start = Some(now - elapsed);
+            job.submit_time = job.submit_time.min(now - elapsed);
+            job.run_time = (elapsed + job.run_time).min(job.time_limit);
+            if job.run_time == job.time_limit {
+                job.outcome = "TIMEOUT";
+            }
+        }
+        Simulator {
+            state: Mutex::new(state),
+        }
+    }
+}
+
+// sbatch options from the script's #SBATCH lines and then the given
+// options, by long name without the dashes
+fn sbatch_options(script: &str, options: &[(String, String)]) -> HashMap<String, String> {
+    let long_name = |option: &str| {
+        match option {
+            "-J" => "job-name",
+            "-p" => "partition",
+            "-t" => "time",
+            "-n" => "ntasks",
+            "-c" => "cpus-per-task",
+            "-a" => "array",
+            "-A" => "account",
+            "-D" => "chdir",
+            "-w" => "nodelist",
+            "-x" => "exclude",
+            "-q" => "qos",
+            option => option.trim_start_matches('-'),
+        }
+        .to_string()
+    };
+    let mut parsed = HashMap::new();
+    for line in script.lines() {
+        let Some(option) = line.trim().strip_prefix("#SBATCH") else {
+            continue;
+        };
+        let option = option.trim();
+        let (key, value) = option.split_once(['=', ' ']).unwrap_or((option, ""));
+        parsed.insert(long_name(key), value.trim().to_string());
+    }
+    for (key, value) in options.iter().filter(|(_, v)| !v.is_empty()) {
+        parsed.insert(long_name(key), value.clone());
+    }
+    parsed
+}
+
+// e.g. 0-9%2, 1,3,5 or 7, with at most MAX_ARRAY_TASKS tasks
+fn parse_array(array: &str) -> Option<Vec<u32>> {
+    let array = array.split('%').next()?;
+    let mut tasks = vec![];
+    for part in array.split(',') {
+        let (from, to) = match part.split_once('-') {
+            Some((from, to)) => (from.parse::<u32>().ok()?, to.parse::<u32>().ok()?),
+            None => (part.parse().ok()?, part.parse().ok()?),
+        };
+        // checked before adding them, as a range can be huge
+        if from > to || tasks.len() as u64 + (to - from) as u64 >= MAX_ARRAY_TASKS as u64 {
+            return None;
+        }
+        tasks.extend(from..=to);
+    }
+    Some(tasks)
+}
+
+// Slurm time limits are minutes unless given as [days-]hours:minutes...
+fn parse_time_limit(time: &str) -> Option<TimeDelta> {
+    match time.parse::<i64>() {
+        Ok(minutes) => Some(TimeDelta::minutes(minutes)),
+        Err(_) => parse_duration(time),
+    }
+}
+
+// Apply one scontrol update key and value to a pending job
+fn update(job: &mut SimJob, key: &str, value: &str, partitions: &[String]) -> Result<(), String> {
+    let invalid = || format!("Invalid {} specification: {}", key, value);
+    match key {
+        "TimeLimit" => job.time_limit = parse_time_limit(value).ok_or_else(invalid)?,
+        "Partition" if partitions.iter().any(|p| p == value) => job.partition = value.to_string(),
+        "Partition" => return Err(format!("Invalid partition name specified: {}", value)),
+        "NumTasks" => job.num_tasks = value.parse().map_err(|_| invalid())?,
+        "QOS" => job.qos = value.to_string(),
+        "Name" => job.name = value.to_string(),
+        "Nice" => job.nice = value.parse().map_err(|_| invalid())?,
+        "ReqNodeList" => job.req_nodes = value.to_string(),
+        "ExcNodeList" => job.exc_nodes = value.to_string(),
+        _ => {
+            return Err(format!(
+                "Update of this parameter is not supported: {}",
+                key
+            ))
+        }
+    }
+    Ok(())
+}
+
+// New jobs are submitted as you, as with sbatch, or as one of the made
+// up users when that can't be told
+fn current_user(config: &SimulatorConfig) -> String {
+    std::env::var("USER").unwrap_or(config.users[0].clone())
+}
+
+impl Backend for Simulator {
+    fn get_jobs(&self, finished: bool) -> Result<Vec<Job>, String> {
+        let mut state = self.state.lock().unwrap();
+        let now = now();
+        state.step(now);
+        Ok(state
+            .jobs
+            .iter()
+            .filter(|j| j.end.is_none_or(|end| finished && now - end < MIN_JOB_AGE))
+            .map(|j| j.to_job(now, &state.nodes, &state.config.gpu_type))
+            .collect())
+    }
+
+    fn get_steps(&self, job_id: &str, _live: bool) -> Result<Vec<Step>, String> {
+        let state = self.state.lock().unwrap();
+        let job = state.find(job_id)?;
+        let Some(node) = job.node else {
+            return Ok(vec![]);
+        };
+        let step = |id: &str, command: String| Step {
+            id: format!("{}.{}", job_id, id),
+            state: job.state.to_string(),
+            nodes: state.nodes[node].name.clone(),
+            elapsed: format_duration(job.elapsed(now())),
+            command,
+        };
+        Ok(vec![
+            step("batch", "batch".to_string()),
+            step("0", format!("python {}.py", job.name)),
+        ])
+    }
+
+    fn get_usage(&self, job_id: &str) -> Result<Vec<StepUsage>, String> {
+        let state = self.state.lock().unwrap();
+        let job = state.find(job_id)?;
+        if job.state != "RUNNING" {
+            return Err(format!("sstat: job {} is not running", job_id));
+        }
+        let elapsed = job.elapsed(now());
+        let cpu = TimeDelta::seconds((elapsed.num_seconds() as f64 * job.usage) as i64);
+        let rss = (job.mem() as f64 * job.usage) as u64;
+        Ok(vec![StepUsage {
+            id: format!("{}.0", job_id),
+            ave_cpu: format_duration(cpu),
+            max_rss: format_mem(rss),
+            ave_rss: format_mem(rss * 9 / 10),
+            max_disk_read: format_mem(rss / 4),
+            max_disk_write: format_mem(rss / 16),
+            tres_usage: format!("cpu={},mem={}", format_duration(cpu), format_mem(rss)),
+        }])
+    }
+
+    fn get_job_efficiency(&self, job_id: &str) -> Result<Efficiency, String> {
+        let state = self.state.lock().unwrap();
+        let job = state.find(job_id)?;
+        if job.end.is_none() {
+            return Err(format!("job {} has not finished", job_id));
+        }
+        Ok(efficiency(job))
+    }
+
+    fn get_user_efficiency(&self) -> Result<HashMap<String, Efficiency>, String> {
+        let state = self.state.lock().unwrap();
+        let mut user_efficiency: HashMap<String, Efficiency> = HashMap::new();
+        for job in state.jobs.iter().filter(|j| j.end.is_some()) {
+            user_efficiency
+                .entry(job.user.clone())
+                .or_default()
+                .add(&efficiency(job));
+        }
+        Ok(user_efficiency)
+    }
+
+    fn show_job(&self, job_id: &str) -> Result<Vec<(String, String)>, String> {
+        let state = self.state.lock().unwrap();
+        let job = state.find(job_id)?;
+        let now = now();
+        let node = job
+            .node
+            .map_or("(null)".to_string(), |n| state.nodes[n].name.clone());
+        let pairs = [
+            ("JobId", job.id.to_string()),
+            ("JobName", job.name.clone()),
+            ("UserId", job.user.clone()),
+            ("Account", job.account.clone()),
+            ("QOS", job.qos.clone()),
+            ("JobState", job.state.to_string()),
+            ("Reason", job.reason.to_string()),
+            ("Priority", job.priority().to_string()),
+            ("Nice", job.nice.to_string()),
+            ("RunTime", format_duration(job.elapsed(now))),
+            ("TimeLimit", format_duration(job.time_limit)),
+            ("SubmitTime", timestamp(Some(job.submit_time))),
+            ("StartTime", timestamp(job.start)),
+            ("EndTime", timestamp(job.end)),
+            ("Partition", job.partition.clone()),
+            ("NodeList", node),
+            ("NumTasks", job.num_tasks.to_string()),
+            ("CPUs/Task", job.cpus.to_string()),
+            ("MinMemoryNode", format!("{}G", job.mem() >> 30)),
+            ("TRES", job.tres()),
+            ("WorkDir", job.work_dir.clone()),
+        ];
+        Ok(pairs
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+
+    fn get_batch_script(&self, job_id: &str) -> Result<String, String> {
+        let state = self.state.lock().unwrap();
+        Ok(state.find(job_id)?.script.clone())
+    }
+
+    fn update_job(&self, job_id: &str, changes: &[(String, String)]) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let partitions = state.config.partitions.clone();
+        let jobs: Vec<&mut SimJob> = state
+            .jobs
+            .iter_mut()
+            .filter(|j| j.matches(job_id) && !is_finished(j.state))
+            .collect();
+        if jobs.is_empty() {
+            return Err(format!("Invalid job id specified: {}", job_id));
+        }
+        // as scontrol, only touch the tasks of an array which are pending
+        let mut updated = 0;
+        for job in jobs.into_iter().filter(|j| j.state == "PENDING") {
+            for (key, value) in changes {
+                update(job, key, value, &partitions)?;
+            }
+            updated += 1;
+        }
+        match updated {
+            0 => Err(format!(
+                "Job is no longer pending execution for job {}",
+                job_id
+            )),
+            _ => {
+                state.schedule(now());
+                Ok(())
+            }
+        }
+    }
+
+    fn submit(&self, script: &str, options: &[(String, String)]) -> Result<String, String> {
+        let mut state = self.state.lock().unwrap();
+        let options = sbatch_options(script, options);
+        let get = |key: &str| options.get(key).map(String::as_str);
+
+        let user = current_user(&state.config);
+        let mut job = state.new_job(&user, now());
+        job.script = script.to_string();
+        job.name = get("job-name").unwrap_or("sbatch").to_string();
+        if let Some(partition) = get("partition") {
+            if !state.config.partitions.iter().any(|p| p == partition) {
+                return Err(format!("invalid partition specified: {}", partition));
+            }
+            job.partition = partition.to_string();
+        }
+        if let Some(time) = get("time") {
+            job.time_limit = parse_time_limit(time)
+                .ok_or(format!("Invalid time limit specification: {}", time))?;
+        }
+        job.run_time = job.run_time.min(job.time_limit);
+        // e.g. gpu:2, gpu:a100:2
+        if let Some(gres) = get("gres") {
+            let count = gres.rsplit(':').next().unwrap_or_default();
+            job.gpus = count
+                .parse()
+                .map_err(|_| format!("Invalid gres specification: {}", gres))?;
+        }
+        if let Some(tasks) = get("ntasks") {
+            job.num_tasks = tasks
+                .parse()
+                .map_err(|_| format!("Invalid ntasks: {}", tasks))?;
+        }
+        if let Some(cpus) = get("cpus-per-task") {
+            job.cpus = cpus
+                .parse()
+                .map_err(|_| format!("Invalid cpus-per-task: {}", cpus))?;
+        }
+        job.cpus = job.cpus.max(job.num_tasks);
+        if job.gpus > state.config.gpus_per_node || job.cpus > CPUS_PER_NODE {
+            return Err("Requested node configuration is not available".to_string());
+        }
+        for (key, field) in [
+            ("account", &mut job.account),
+            ("qos", &mut job.qos),
+            ("chdir", &mut job.work_dir),
+            ("nodelist", &mut job.req_nodes),
+            ("exclude", &mut job.exc_nodes),
+        ] {
+            if let Some(value) = get(key) {
+                *field = value.to_string();
+            }
+        }
+        if let Some(nice) = get("nice") {
+            job.nice = nice
+                .parse()
+                .map_err(|_| format!("Invalid nice value: {}", nice))?;
+        }
+        let tasks = match get("array") {
+            Some(array) => match parse_array(array) {
+                Some(tasks) => Some(tasks),
+                None => return Err(format!("Invalid job array specification: {}", array)),
+            },
+            None => None,
+        };
+
+        let job_id = job.id.to_string();
+        state.push(job, tasks);
+        state.schedule(now());
+        Ok(job_id)
+    }
+
+    // a line of made up progress every half a minute the job has run,
+    // and how it ended if it didn't complete
+    fn get_log(&self, job_id: &str, path: &str) -> Result<String, String> {
+        let state = self.state.lock().unwrap();
+        let job = state.find(job_id)?;
+        let Some(start) = job.start else {
+            return Err(format!(
+                "tail failed: tail: cannot open '{}' for reading: No such file or directory",
+                path
+            ));
+        };
+        let steps = (job.elapsed(now()).num_seconds() / 30) as usize;
+        let mut log: Vec<String> = (steps.saturating_sub(LOG_LINES)..steps)
+            .map(|i| {
+                let time = start + TimeDelta::seconds(30 * (i as i64 + 1));
+                let loss = 2.5 * 0.98f64.powi(i as i32);
+                format!(
+                    "[{}] {} step {} loss {:.4}",
+                    time.format("%H:%M:%S"),
+                    job.name,
+                    i * 100,
+                    loss
+                )
+            })
+            .collect();
+        match job.state {
+            "FAILED" => log.push(
+                "RuntimeError: CUDA error: an illegal memory access was encountered".to_string(),
+            ),
+            "OUT_OF_MEMORY" => log.push(format!(
+                "slurmstepd: error: Detected 1 oom_kill event in StepId={}.batch.",
+                job_id
+            )),
+            "TIMEOUT" => log.push(format!(
+                "slurmstepd: error: *** JOB {} ON {} CANCELLED AT {} DUE TO TIME LIMIT ***",
+                job_id,
+                job.node.map_or("", |n| &state.nodes[n].name),
+                timestamp(job.end)
+            )),
+            "CANCELLED" => log.push(format!(
+                "slurmstepd: error: *** JOB {} ON {} CANCELLED AT {} ***",
+                job_id,
+                job.node.map_or("", |n| &state.nodes[n].name),
+                timestamp(job.end)
+            )),
+            _ => (),
+        }
+        Ok(log.join("\n"))
+    }
+
+    fn current_user(&self) -> Result<String, String> {
+        Ok(current_user(&self.state.lock().unwrap().config))
+    }
+
+    fn cancel_job(&self, job_id: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let now = now();
+        for i in state.active(job_id)? {
+            state.free(i);
+            let job = &mut state.jobs[i];
+            job.state = "CANCELLED";
+            job.reason = "None";
+            job.end = Some(now);
+        }
+        state.schedule(now);
+        Ok(())
+    }
+
+    // as scontrol, only the pending tasks of an array are held
+    fn hold_job(&self, job_id: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let mut held = 0;
+        for i in state.active(job_id)? {
+            let job = &mut state.jobs[i];
+            if job.state == "PENDING" {
+                job.held = true;
+                job.reason = "JobHeldUser";
+                held += 1;
+            }
+        }
+        match held {
+            0 => Err(format!(
+                "Job is no longer pending execution for job {}",
+                job_id
+            )),
+            _ => {
+                state.schedule(now());
+                Ok(())
+            }
+        }
+    }
+
+    fn release_job(&self, job_id: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        for i in state.active(job_id)? {
+            let job = &mut state.jobs[i];
+            if job.held {
+                job.held = false;
+                job.reason = "Priority";
+            }
+        }
+        state.schedule(now());
+        Ok(())
+    }
+
+    // every node is up, and the first partition is the default
+    fn get_partitions(&self) -> Result<Vec<Partition>, String> {
+        let state = self.state.lock().unwrap();
+        let partitions = state.config.partitions.iter().enumerate();
+        Ok(partitions
+            .map(|(i, name)| {
+                let mut nodes = Counts::default();
+                let mut cpus = Counts::default();
+                for node in state.nodes.iter().filter(|n| &n.partition == name) {
+                    match node.free_cpus {
+                        CPUS_PER_NODE => nodes.idle += 1,
+                        _ => nodes.allocated += 1,
+                    }
+                    nodes.total += 1;
+                    cpus.allocated += CPUS_PER_NODE - node.free_cpus;
+                    cpus.idle += node.free_cpus;
+                    cpus.total += CPUS_PER_NODE;
+                }
+                Partition {
+                    cluster: String::new(),
+                    name: format!("{}{}", name, if i == 0 { "*" } else { "" }),
+                    state: "up".to_string(),
+                    nodes,
+                    cpus,
+                }
+            })
+            .collect())
+    }
+}
+
+fn efficiency(job: &SimJob) -> Efficiency {
+    let allocated = job.elapsed(job.end.unwrap_or(job.submit_time)) * job.cpus as i32;
+    Efficiency {
+        cpu_used: TimeDelta::seconds((allocated.num_seconds() as f64 * job.usage) as i64),
+        cpu_allocated: allocated,
+        mem_used: (job.mem() as f64 * job.usage.sqrt()) as u64,
+        mem_requested: job.mem(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one node with room for one 4 GPU job at a time, and no made up
+    // users submitting more
+    fn config() -> SimulatorConfig {
+        SimulatorConfig {
+            nodes: 1,
+            gpus_per_node: 4,
+            partitions: vec!["gpu".to_string()],
+            jobs: 0,
+            seed: Some(1),
+            ..SimulatorConfig::default()
+        }
+    }
+
+    const SCRIPT: &str = "#!/bin/bash\n#SBATCH --gres=gpu:4\n./train.sh\n";
+
+    fn state_of(simulator: &Simulator, job_id: &str) -> (String, String) {
+        let job = simulator
+            .get_jobs(true)
+            .unwrap()
+            .into_iter()
+            .find(|j| j.JobID == job_id)
+            .unwrap();
+        (job.State, job.Reason)
+    }
+
+    #[test]
+    fn jobs_run_and_end() {
+        let mut state = State::new(&config());
+        let start = now();
+        for (outcome, run_time) in [("COMPLETED", 60), ("FAILED", 30)] {
+            let mut job = state.new_job("alice", start);
+            job.gpus = 4;
+            job.outcome = outcome;
+            job.run_time = TimeDelta::seconds(run_time);
+            state.push(job, None);
+        }
+        let states = |state: &State| -> Vec<(&str, &str)> {
+            state.jobs.iter().map(|j| (j.state, j.reason)).collect()
+        };
+
+        state.schedule(start);
+        assert_eq!(
+            states(&state),
+            [("RUNNING", "None"), ("PENDING", "Resources")]
+        );
+        state.step(start + TimeDelta::seconds(61));
+        assert_eq!(states(&state), [("COMPLETED", "None"), ("RUNNING", "None")]);
+        assert_eq!(state.jobs[0].end, Some(start + TimeDelta::seconds(60)));
+        state.step(start + TimeDelta::seconds(100));
+        assert_eq!(
+            states(&state),
+            [("COMPLETED", "None"), ("FAILED", "NonZeroExitCode")]
+        );
+        assert_eq!(state.nodes[0].free_gpus, 4);
+    }
+
+    #[test]
+    fn finished_jobs_only_when_asked() {
+        let simulator = Simulator::new(&config());
+        let job_id = simulator.submit(SCRIPT, &[]).unwrap();
+        simulator.cancel_job(&job_id).unwrap();
+        assert!(simulator.get_jobs(false).unwrap().is_empty());
+        assert_eq!(state_of(&simulator, &job_id).0, "CANCELLED");
+    }
+
+    #[test]
+    fn hold_and_release() {
+        let simulator = Simulator::new(&config());
+        let running = simulator.submit(SCRIPT, &[]).unwrap();
+        let pending = simulator.submit(SCRIPT, &[]).unwrap();
+
+        // only pending jobs can be held
+        assert!(simulator.hold_job(&running).is_err());
+        simulator.hold_job(&pending).unwrap();
+        assert_eq!(
+            state_of(&simulator, &pending),
+            ("PENDING".to_string(), "JobHeldUser".to_string())
+        );
+        // held jobs stay put when there is room
+        simulator.cancel_job(&running).unwrap();
+        assert_eq!(state_of(&simulator, &pending).0, "PENDING");
+
+        simulator.release_job(&pending).unwrap();
+        assert_eq!(state_of(&simulator, &pending).0, "RUNNING");
+        assert!(simulator.hold_job("9999").is_err());
+    }
+
+    #[test]
+    fn submit_options() {
+        let script = "#!/bin/bash\n\
+                      #SBATCH -J train\n\
+                      #SBATCH --time=2:00:00\n\
+                      #SBATCH --gres gpu:a100:2\n\
+                      #SBATCH --partition=debug\n";
+        let given = [
+            ("--partition".to_string(), "gpu".to_string()),
+            ("--nice".to_string(), String::new()),
+        ];
+        let options = sbatch_options(script, &given);
+        let get = |key: &str| options.get(key).map(String::as_str);
+        assert_eq!(get("job-name"), Some("train"));
+        assert_eq!(get("time"), Some("2:00:00"));
+        assert_eq!(get("gres"), Some("gpu:a100:2"));
+        // given options win, unless they are left empty
+        assert_eq!(get("partition"), Some("gpu"));
+        assert_eq!(get("nice"), None);
+
+        assert_eq!(parse_time_limit("90"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_time_limit("1-00:00:00"), Some(TimeDelta::days(1)));
+
+        let simulator = Simulator::new(&config());
+        let job_id = simulator.submit(script, &given).unwrap();
+        let info = simulator.show_job(&job_id).unwrap();
+        assert!(info.contains(&("JobName".to_string(), "train".to_string())));
+        for (option, value) in [
+            ("--partition", "nowhere"),
+            ("--time", "soon"),
+            ("--gres", "gpu:8"),
+            ("--array", "0-4000000000"),
+        ] {
+            let given = [(option.to_string(), value.to_string())];
+            assert!(simulator.submit(script, &given).is_err(), "{}", option);
+        }
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(parse_array("0-3%2"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_array("1,3,5-6"), Some(vec![1, 3, 5, 6]));
+        assert_eq!(parse_array("7"), Some(vec![7]));
+        assert_eq!(parse_array("0-999").map(|t| t.len()), Some(1000));
+        for array in ["", "x", "3-1", "0-1000", "0-4000000000", "0-500,0-500"] {
+            assert_eq!(parse_array(array), None, "{}", array);
+        }
+    }
+}
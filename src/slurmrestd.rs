@@ -0,0 +1,817 @@
+use chrono::{Local, TimeDelta};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, env, time::Duration};
+use ureq::{Agent, RequestBuilder};
+
+use crate::backend::Backend;
+use crate::config::SlurmRestdConfig;
+use crate::jobs::{is_finished, Job};
+use crate::partitions::{Counts, Partition};
+use crate::squeue_json::{jobs_from_json, value_text};
+use crate::steps::Step;
+use crate::time::{format_duration, parse_duration};
+use crate::usage::{format_mem, parse_mem, Efficiency, StepUsage};
+
+// Slurm's REST API, for running lazyslurm away from the cluster. It
+// is authenticated with a JWT (from `scontrol token`), taken from
+// SLURM_JWT or the config. Paths are for API version v0.0.40 and later;
+// older versions lay out some requests differently.
+pub struct SlurmRestd {
+    agent: Agent,
+    url: String,
+    api_version: String,
+    token: String,
+    user: Option<String>,
+}
+
+impl SlurmRestd {
+    pub fn new(config: &SlurmRestdConfig) -> Result<Self, String> {
+        let token = env::var("SLURM_JWT")
+            .ok()
+            .filter(|t| !t.is_empty())
+            .or(config.token.clone())
+            .ok_or("slurmrestd needs a token, in SLURM_JWT or the config")?;
+        let agent = Agent::config_builder()
+            // slurmrestd explains what went wrong in the body
+            .http_status_as_error(false)
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into();
+        Ok(SlurmRestd {
+            agent,
+            url: config.url.trim_end_matches('/').to_string(),
+            api_version: config.api_version.clone(),
+            token,
+            user: config.user.clone(),
+        })
+    }
+
+    // `path` is relative to the server, with {version} standing in for the
+    // API version, e.g. slurm/{version}/jobs. Requests with a body are
+    // POSTed, others are GETs.
+    fn request(&self, path: &str, body: Option<Value>) -> Result<Value, String> {
+        let url = self.url(path);
+        let response = match body {
+            Some(body) => self
+                .with_auth(self.agent.post(&url))
+                .content_type("application/json")
+                .send(body.to_string()),
+            None => self.with_auth(self.agent.get(&url)).call(),
+        };
+        self.response(response)
+    }
+
+    fn delete(&self, path: &str) -> Result<Value, String> {
+        let response = self.with_auth(self.agent.delete(&self.url(path))).call();
+        self.response(response)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.url,
+            path.replace("{version}", &self.api_version)
+        )
+    }
+
+    fn response(
+        &self,
+        response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+    ) -> Result<Value, String> {
+        let mut response = response.map_err(|e| format!("slurmrestd: {}", e))?;
+        let status = response.status();
+        let body = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| format!("slurmrestd: {}", e))?;
+        let value: Value = serde_json::from_str(&body).map_err(|_| {
+            format!(
+                "slurmrestd: {} {}",
+                status,
+                body.lines().next().unwrap_or("")
+            )
+        })?;
+        // errors come as [{"error": "...", "description": "..."}]
+        let errors: Vec<String> = value
+            .get("errors")
+            .and_then(|e| e.as_array())
+            .into_iter()
+            .flatten()
+            .map(|e| {
+                let description = value_text(e.get("description").unwrap_or(&Value::Null));
+                match description.as_str() {
+                    "" => value_text(e.get("error").unwrap_or(&Value::Null)),
+                    _ => description,
+                }
+            })
+            .collect();
+        if !errors.is_empty() || !status.is_success() {
+            return Err(format!("slurmrestd: {} {}", status, errors.join(", ")));
+        }
+        Ok(value)
+    }
+
+    fn with_auth<B>(&self, request: RequestBuilder<B>) -> RequestBuilder<B> {
+        let request = request.header("X-SLURM-USER-TOKEN", &self.token);
+        match &self.user {
+            Some(user) => request.header("X-SLURM-USER-NAME", user),
+            None => request,
+        }
+    }
+
+    fn get_job(&self, job_id: &str) -> Result<Value, String> {
+        let response = self.request(&format!("slurm/{{version}}/job/{}", job_id), None)?;
+        first_job(response, job_id)
+    }
+
+    // The job as the accounting database has it, with its steps
+    fn get_db_job(&self, job_id: &str) -> Result<Value, String> {
+        let response = self.request(&format!("slurmdb/{{version}}/job/{}", job_id), None)?;
+        first_job(response, job_id)
+    }
+}
+
+fn first_job(response: Value, job_id: &str) -> Result<Value, String> {
+    match response.get("jobs").and_then(|j| j.get(0)) {
+        Some(job) => Ok(job.clone()),
+        None => Err(format!("slurmrestd has no record of job {}", job_id)),
+    }
+}
+
+// The first of a list of states, e.g. {"current": ["COMPLETED"]}
+fn state(value: &Value) -> String {
+    let state = value.get("current").unwrap_or(value);
+    match state {
+        Value::Array(states) => states.first().map(value_text).unwrap_or_default(),
+        _ => value_text(state),
+    }
+}
+
+// Times are either plain seconds or {"seconds": 1, "microseconds": 0}
+fn seconds(value: Option<&Value>) -> TimeDelta {
+    let seconds = match value {
+        Some(Value::Object(o)) if o.contains_key("seconds") => o.get("seconds"),
+        value => value,
+    };
+    TimeDelta::seconds(
+        seconds
+            .map(value_text)
+            .unwrap_or_default()
+            .parse()
+            .unwrap_or(0),
+    )
+}
+
+// A count from a list of TRES like [{"type": "mem", "count": 1024}],
+// where `tres` is the type, or type/name as in fs/disk
+fn tres_count(list: Option<&Value>, tres: &str) -> Option<u64> {
+    list?.as_array()?.iter().find_map(|t| {
+        let name = match (value_text(&t["type"]), value_text(&t["name"])) {
+            (kind, name) if name.is_empty() => kind,
+            (kind, name) => format!("{}/{}", kind, name),
+        };
+        match name == tres {
+            true => value_text(&t["count"]).parse().ok(),
+            false => None,
+        }
+    })
+}
+
+// As sacct prints TRES usage, e.g. cpu=0:05,mem=1.0G. CPU time is
+// counted in milliseconds, memory and disk in bytes.
+fn tres_usage(list: Option<&Value>) -> String {
+    let Some(list) = list.and_then(|l| l.as_array()) else {
+        return String::new();
+    };
+    list.iter()
+        .map(|t| {
+            let (kind, name) = (value_text(&t["type"]), value_text(&t["name"]));
+            let count: u64 = value_text(&t["count"]).parse().unwrap_or(0);
+            let value = match kind.as_str() {
+                "cpu" => format_duration(TimeDelta::milliseconds(count as i64)),
+                "mem" | "fs" => format_mem(count),
+                _ => count.to_string(),
+            };
+            match name.as_str() {
+                "" => format!("{}={}", kind, value),
+                _ => format!("{}/{}={}", kind, name, value),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Before v0.0.40 a step's ID was {"job_id": 1, "step_id": "batch"}
+fn step_id(step: &Value) -> String {
+    match &step["step"]["id"] {
+        Value::Object(id) => format!(
+            "{}.{}",
+            value_text(id.get("job_id").unwrap_or(&Value::Null)),
+            value_text(id.get("step_id").unwrap_or(&Value::Null))
+        ),
+        id => value_text(id),
+    }
+}
+
+fn steps(job: &Value) -> Vec<Step> {
+    job["steps"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|step| Step {
+            id: step_id(step),
+            state: state(&step["state"]),
+            nodes: value_text(&step["nodes"]["range"]),
+            elapsed: format_duration(seconds(step["time"].get("elapsed"))),
+            command: value_text(&step["step"]["name"]),
+        })
+        .collect()
+}
+
+// slurmdbd only hears how much a step has used once it ends, so unlike
+// sstat this says little about steps which are running. What sacct
+// calls TRESUsageIn is "requested" here, and TRESUsageOut "consumed".
+fn usage(job: &Value) -> Vec<StepUsage> {
+    let mem =
+        |list: Option<&Value>, tres: &str| tres_count(list, tres).map_or(String::new(), format_mem);
+    job["steps"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|step| {
+            let usage_in = &step["tres"]["requested"];
+            let usage_out = &step["tres"]["consumed"];
+            let ave_cpu = tres_count(usage_in.get("average"), "cpu").unwrap_or(0);
+            StepUsage {
+                id: step_id(step),
+                ave_cpu: format_duration(TimeDelta::milliseconds(ave_cpu as i64)),
+                max_rss: mem(usage_in.get("max"), "mem"),
+                ave_rss: mem(usage_in.get("average"), "mem"),
+                max_disk_read: mem(usage_in.get("max"), "fs/disk"),
+                max_disk_write: mem(usage_out.get("max"), "fs/disk"),
+                tres_usage: tres_usage(usage_in.get("total")),
+            }
+        })
+        .collect()
+}
+
+// As sacct reports it: the CPU time used out of the time the job's
+// CPUs were allocated for, and the peak memory of any of its steps out
+// of what it was allocated on each node.
+fn efficiency(job: &Value) -> Efficiency {
+    let allocated = job["tres"].get("allocated");
+    let cpus = tres_count(allocated, "cpu").unwrap_or(0);
+    let nodes = tres_count(allocated, "node").unwrap_or(1).max(1);
+    let mem_used = job["steps"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|step| tres_count(step["tres"]["requested"].get("max"), "mem"))
+        .max()
+        .unwrap_or(0);
+    Efficiency {
+        cpu_used: seconds(job["time"].get("total")),
+        cpu_allocated: seconds(job["time"].get("elapsed")) * cpus as i32,
+        mem_used,
+        // allocated memory is in megabytes
+        mem_requested: (tres_count(allocated, "mem").unwrap_or(0) << 20) / nodes,
+    }
+}
+
+// The API has no summary like sinfo's, so the nodes of each partition
+// are counted up here. As in sinfo, mixed nodes count as allocated, and
+// drained or unresponsive ones as neither allocated nor idle.
+fn partitions(partitions: &Value, nodes: &Value) -> Vec<Partition> {
+    let nodes = nodes["nodes"].as_array().map_or(&[][..], |n| n.as_slice());
+    let has = |list: &Value, item: &str| {
+        list.as_array()
+            .into_iter()
+            .flatten()
+            .any(|i| i.as_str() == Some(item))
+    };
+    partitions["partitions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|partition| {
+            let name = value_text(&partition["name"]);
+            let mut counts = (Counts::default(), Counts::default());
+            for node in nodes.iter().filter(|n| has(&n["partitions"], &name)) {
+                let cpus = node["cpus"].as_u64().unwrap_or(0) as u32;
+                let allocated = node["alloc_cpus"].as_u64().unwrap_or(0) as u32;
+                // a node can briefly report more allocated CPUs than it has
+                let free = cpus.saturating_sub(allocated);
+                let unusable = ["DRAIN", "NOT_RESPONDING", "FAIL"]
+                    .iter()
+                    .any(|flag| has(&node["state"], flag));
+                let (nodes, node_cpus) = &mut counts;
+                nodes.total += 1;
+                node_cpus.total += cpus;
+                node_cpus.allocated += allocated;
+                match state(&node["state"]).as_str() {
+                    _ if unusable => {
+                        nodes.other += 1;
+                        node_cpus.other += free;
+                    }
+                    "ALLOCATED" | "MIXED" | "COMPLETING" => {
+                        nodes.allocated += 1;
+                        node_cpus.idle += free;
+                    }
+                    "IDLE" => {
+                        nodes.idle += 1;
+                        node_cpus.idle += free;
+                    }
+                    _ => {
+                        nodes.other += 1;
+                        node_cpus.other += free;
+                    }
+                }
+            }
+            let default = if has(&partition["flags"], "DEFAULT") {
+                "*"
+            } else {
+                ""
+            };
+            Partition {
+                cluster: String::new(),
+                name: format!("{}{}", name, default),
+                state: state(&partition["partition"]["state"]).to_lowercase(),
+                nodes: counts.0,
+                cpus: counts.1,
+            }
+        })
+        .collect()
+}
+
+fn unsupported<T>(what: &str) -> Result<T, String> {
+    Err(format!("{} is not available from slurmrestd", what))
+}
+
+// Time limits are given to the API in minutes
+fn minutes(duration: &str) -> Result<Value, String> {
+    match duration {
+        "UNLIMITED" | "INFINITE" => Ok(json!({"set": true, "infinite": true})),
+        _ => {
+            let duration =
+                parse_duration(duration).ok_or(format!("invalid time limit {}", duration))?;
+            Ok(json!((duration.num_seconds() + 59) / 60))
+        }
+    }
+}
+
+// Memory is given to the API in megabytes, which is also what sbatch
+// assumes without a unit
+fn megabytes(mem: &str) -> Result<Value, String> {
+    let megabytes = match mem.parse::<u64>() {
+        Ok(megabytes) => Some(megabytes),
+        Err(_) => parse_mem(mem).map(|bytes| bytes.div_ceil(1 << 20)),
+    };
+    megabytes
+        .map(Value::from)
+        .ok_or(format!("invalid memory size {}", mem))
+}
+
+fn integer(value: &str) -> Result<Value, String> {
+    value
+        .parse::<i64>()
+        .map(Value::from)
+        .map_err(|_| format!("{} is not a whole number", value))
+}
+
+impl Backend for SlurmRestd {
+    // like squeue --json, this lists every job slurmctld knows about
+    fn get_jobs(&self, finished: bool) -> Result<Vec<Job>, String> {
+        let mut jobs = jobs_from_json(&self.request("slurm/{version}/jobs", None)?)?;
+        jobs.retain(|j| finished || !is_finished(&j.State));
+        Ok(jobs)
+    }
+
+    // Steps, usage and efficiency all come from the accounting database
+    fn get_steps(&self, job_id: &str, _live: bool) -> Result<Vec<Step>, String> {
+        Ok(steps(&self.get_db_job(job_id)?))
+    }
+
+    fn get_usage(&self, job_id: &str) -> Result<Vec<StepUsage>, String> {
+        Ok(usage(&self.get_db_job(job_id)?))
+    }
+
+    fn get_job_efficiency(&self, job_id: &str) -> Result<Efficiency, String> {
+        Ok(efficiency(&self.get_db_job(job_id)?))
+    }
+
+    // Like the command line version, the jobs which finished in the last day
+    fn get_user_efficiency(&self) -> Result<HashMap<String, Efficiency>, String> {
+        let since = Local::now().timestamp() - 24 * 60 * 60;
+        let path = format!("slurmdb/{{version}}/jobs?start_time={}", since);
+        let response = self.request(&path, None)?;
+        let mut user_efficiency: HashMap<String, Efficiency> = HashMap::new();
+        for job in response["jobs"].as_array().into_iter().flatten() {
+            let finished = matches!(
+                state(&job["state"]).as_str(),
+                "COMPLETED" | "FAILED" | "TIMEOUT" | "OUT_OF_MEMORY" | "CANCELLED"
+            );
+            if finished {
+                let user = value_text(&job["user"]);
+                user_efficiency
+                    .entry(user)
+                    .or_default()
+                    .add(&efficiency(job));
+            }
+        }
+        Ok(user_efficiency)
+    }
+
+    // Everything slurmrestd knows about the job, as it names it. Nested
+    // values are shown as JSON.
+    fn show_job(&self, job_id: &str) -> Result<Vec<(String, String)>, String> {
+        let Value::Object(job) = self.get_job(job_id)? else {
+            return Err(format!("slurmrestd has no record of job {}", job_id));
+        };
+        Ok(job
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match &value {
+                    Value::Array(_) => value.to_string(),
+                    Value::Object(o) if !o.contains_key("number") => value.to_string(),
+                    _ => value_text(&value),
+                };
+                (key, value)
+            })
+            .collect())
+    }
+
+    // Only stored if the cluster keeps scripts in its accounting database
+    fn get_batch_script(&self, job_id: &str) -> Result<String, String> {
+        let job = self.get_db_job(job_id)?;
+        match job.get("script").and_then(|s| s.as_str()) {
+            Some(script) if !script.is_empty() => Ok(script.to_string()),
+            _ => Err(format!("No batch script stored for job {}", job_id)),
+        }
+    }
+
+    fn update_job(&self, job_id: &str, changes: &[(String, String)]) -> Result<(), String> {
+        let mut update = Map::new();
+        for (key, value) in changes {
+            let (field, value) = match key.as_str() {
+                "TimeLimit" => ("time_limit", minutes(value)?),
+                "Partition" => ("partition", json!(value)),
+                "NumTasks" => ("tasks", integer(value)?),
+                "QOS" => ("qos", json!(value)),
+                "Name" => ("name", json!(value)),
+                "Nice" => ("nice", integer(value)?),
+                "ReqNodeList" => ("required_nodes", json!(value)),
+                "ExcNodeList" => ("excluded_nodes", json!(value)),
+                _ => return unsupported(&format!("Changing {}", key)),
+            };
+            update.insert(field.to_string(), value);
+        }
+        let path = format!("slurm/{{version}}/job/{}", job_id);
+        self.request(&path, Some(Value::Object(update)))?;
+        Ok(())
+    }
+
+    fn submit(&self, script: &str, options: &[(String, String)]) -> Result<String, String> {
+        let mut job = Map::new();
+        // sbatch would pass on the user's environment, but that is
+        // the environment of this machine rather than the cluster
+        job.insert(
+            "environment".to_string(),
+            json!(["PATH=/usr/local/bin:/usr/bin:/bin"]),
+        );
+        for (option, value) in options.iter().filter(|(_, v)| !v.is_empty()) {
+            let (field, value) = match option.as_str() {
+                "--job-name" => ("name", json!(value)),
+                "--partition" => ("partition", json!(value)),
+                "--time" => ("time_limit", minutes(value)?),
+                "--nodes" => ("minimum_nodes", integer(value)?),
+                "--ntasks" => ("tasks", integer(value)?),
+                "--cpus-per-task" => ("cpus_per_task", integer(value)?),
+                "--mem" => ("memory_per_node", megabytes(value)?),
+                "--mem-per-cpu" => ("memory_per_cpu", megabytes(value)?),
+                "--gres" => {
+                    let gres: Vec<String> =
+                        value.split(',').map(|g| format!("gres/{}", g)).collect();
+                    ("tres_per_node", json!(gres.join(",")))
+                }
+                "--qos" => ("qos", json!(value)),
+                "--account" => ("account", json!(value)),
+                "--chdir" => ("current_working_directory", json!(value)),
+                "--output" => ("standard_output", json!(value)),
+                "--error" => ("standard_error", json!(value)),
+                "--array" => ("array", json!(value)),
+                _ => return unsupported(&format!("The sbatch option {}", option)),
+            };
+            job.insert(field.to_string(), value);
+        }
+        let body = json!({"script": script, "job": job});
+        let response = self.request("slurm/{version}/job/submit", Some(body))?;
+        match response.get("job_id") {
+            Some(job_id) => Ok(value_text(job_id)),
+            None => Err("slurmrestd did not return a job ID".to_string()),
+        }
+    }
+
+    fn get_partitions(&self) -> Result<Vec<Partition>, String> {
+        Ok(partitions(
+            &self.request("slurm/{version}/partitions", None)?,
+            &self.request("slurm/{version}/nodes", None)?,
+        ))
+    }
+
+    // slurmrestd says who the token is for alongside every response
+    fn current_user(&self) -> Result<String, String> {
+        if let Some(user) = &self.user {
+            return Ok(user.clone());
+        }
+        let response = self.request("slurm/{version}/ping", None)?;
+        match response["meta"]["client"]["user"].as_str() {
+            Some(user) if !user.is_empty() => Ok(user.to_string()),
+            _ => Err("slurmrestd did not say who the token is for".to_string()),
+        }
+    }
+
+    fn cancel_job(&self, job_id: &str) -> Result<(), String> {
+        self.delete(&format!("slurm/{{version}}/job/{}", job_id))?;
+        Ok(())
+    }
+
+    fn hold_job(&self, job_id: &str) -> Result<(), String> {
+        let path = format!("slurm/{{version}}/job/{}", job_id);
+        self.request(&path, Some(json!({"hold": true})))?;
+        Ok(())
+    }
+
+    fn release_job(&self, job_id: &str) -> Result<(), String> {
+        let path = format!("slurm/{{version}}/job/{}", job_id);
+        self.request(&path, Some(json!({"hold": false})))?;
+        Ok(())
+    }
+
+    // slurmrestd runs on the cluster, but doesn't serve files
+    fn get_log(&self, _job_id: &str, _path: &str) -> Result<String, String> {
+        unsupported("Job output")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{Action, App};
+    use crate::backend::Cluster;
+    use crate::config::Config;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::sync::Arc;
+    use std::thread;
+
+    const JOBS: &str = include_str!("../tests/fixtures/squeue-23.02.json");
+    const DB_JOB: &str = include_str!("../tests/fixtures/slurmrestd/slurmdb-job-1003.json");
+    const PARTITIONS: &str = include_str!("../tests/fixtures/slurmrestd/partitions.json");
+    const NODES: &str = include_str!("../tests/fixtures/slurmrestd/nodes.json");
+    const NOT_FOUND: &str = r#"{"errors": [{"error": "Unable to query JobId=9999", "description": "Invalid job id specified"}]}"#;
+
+    // A stand-in for slurmrestd on a local port, which answers with the
+    // recorded response for each path, or 404 for any other. Requests
+    // without the token are turned away, as slurmrestd does. Each request
+    // is passed back, headers and all, to check what was sent.
+    fn mock_slurmrestd(routes: &'static [(&str, &str)]) -> (SlurmRestd, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config =
+            SlurmRestdConfig::new(format!("http://{}", listener.local_addr().unwrap()));
+        config.token = Some("secret".to_string());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(n) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = n.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8(body).unwrap());
+
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+                let route = routes.iter().find(|(p, _)| *p == path);
+                let (status, response) = match route {
+                    _ if !request
+                        .to_lowercase()
+                        .contains("x-slurm-user-token: secret") =>
+                    {
+                        ("401 Unauthorized", "Authentication failure")
+                    }
+                    Some((_, response)) => ("200 OK", *response),
+                    None => ("404 Not Found", NOT_FOUND),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                )
+                .unwrap();
+                sender.send(request).unwrap();
+            }
+        });
+        (SlurmRestd::new(&config).unwrap(), receiver)
+    }
+
+    #[test]
+    fn over_http() {
+        let (slurmrestd, requests) = mock_slurmrestd(&[
+            ("/slurm/v0.0.40/jobs", JOBS),
+            ("/slurm/v0.0.40/partitions", PARTITIONS),
+            ("/slurm/v0.0.40/nodes", NODES),
+            ("/slurmdb/v0.0.40/job/1003", DB_JOB),
+            (
+                "/slurm/v0.0.40/ping",
+                r#"{"meta": {"client": {"user": "alice"}}, "pings": []}"#,
+            ),
+            (
+                "/slurm/v0.0.40/job/1001",
+                r#"{"errors": [], "warnings": []}"#,
+            ),
+        ]);
+
+        let all = jobs_from_json(&serde_json::from_str(JOBS).unwrap()).unwrap();
+        let jobs = slurmrestd.get_jobs(false).unwrap();
+        assert!(jobs.iter().all(|j| !is_finished(&j.State)));
+        assert!(jobs.len() < all.len());
+        assert_eq!(slurmrestd.get_jobs(true).unwrap().len(), all.len());
+
+        assert_eq!(slurmrestd.get_steps("1003", false).unwrap().len(), 2);
+        let efficiency = slurmrestd.get_job_efficiency("1003").unwrap();
+        assert_eq!(efficiency.mem_used, 24 << 30);
+        assert_eq!(slurmrestd.get_partitions().unwrap().len(), 2);
+        assert_eq!(slurmrestd.current_user().unwrap(), "alice");
+
+        let changes = [("TimeLimit".to_string(), "2:00:00".to_string())];
+        slurmrestd.update_job("1001", &changes).unwrap();
+        slurmrestd.hold_job("1001").unwrap();
+        slurmrestd.cancel_job("1001").unwrap();
+        let requests: Vec<String> = requests.try_iter().collect();
+        let sent = |method: &str, body: &str| {
+            let request = format!("{} /slurm/v0.0.40/job/1001 ", method);
+            requests
+                .iter()
+                .any(|r| r.starts_with(&request) && r.ends_with(body))
+        };
+        assert!(sent("POST", r#"{"time_limit":120}"#));
+        assert!(sent("POST", r#"{"hold":true}"#));
+        assert!(sent("DELETE", ""));
+
+        // what went wrong comes from the body
+        let e = slurmrestd.show_job("9999").unwrap_err();
+        assert!(e.contains("Invalid job id specified"), "{}", e);
+    }
+
+    #[test]
+    fn resubmit() {
+        let (slurmrestd, requests) = mock_slurmrestd(&[
+            ("/slurm/v0.0.40/jobs", JOBS),
+            ("/slurmdb/v0.0.40/job/1003", DB_JOB),
+            ("/slurm/v0.0.40/job/submit", r#"{"job_id": 2001}"#),
+        ]);
+        let cluster = Cluster {
+            name: String::new(),
+            backend: Arc::new(slurmrestd),
+        };
+        let mut app = App::new(Config::default(), vec![cluster]);
+        app.update(Some(Action::ToggleFinished));
+        while app.selected_job().map(|j| j.JobID.as_str()) != Some("1003") {
+            app.update(Some(Action::Down));
+        }
+        app.update(Some(Action::ResubmitJob));
+        app.update(Some(Action::SubmitForm));
+        assert_eq!(app.status, Some(Ok("Submitted job 2001".to_string())));
+
+        let submit = requests
+            .try_iter()
+            .find(|r| r.starts_with("POST /slurm/v0.0.40/job/submit "))
+            .unwrap();
+        let body: Value = serde_json::from_str(&submit[submit.find('{').unwrap()..]).unwrap();
+        assert!(body["script"].as_str().unwrap().contains("./preprocess.sh"));
+        let job = &body["job"];
+        assert_eq!(job["name"], "preprocess");
+        assert_eq!(job["minimum_nodes"], 1);
+        assert_eq!(job["memory_per_node"], 32 << 10);
+        assert_eq!(job["tres_per_node"], "gres/gpu:a100:2");
+        assert_eq!(job["standard_output"], "/home/carol/slurm-%j.out");
+        assert_eq!(job["standard_error"], "/home/carol/slurm-%j.out");
+    }
+
+    #[test]
+    fn wrong_token() {
+        let (slurmrestd, _) = mock_slurmrestd(&[("/slurm/v0.0.40/jobs", JOBS)]);
+        let slurmrestd = SlurmRestd {
+            token: "expired".to_string(),
+            ..slurmrestd
+        };
+        let e = slurmrestd.get_jobs(false).err().unwrap();
+        assert_eq!(e, "slurmrestd: 401 Unauthorized Authentication failure");
+    }
+
+    fn db_job() -> Value {
+        let json = include_str!("../tests/fixtures/slurmrestd/slurmdb-job-1003.json");
+        first_job(serde_json::from_str(json).unwrap(), "1003").unwrap()
+    }
+
+    #[test]
+    fn steps_from_slurmdb() {
+        let steps: Vec<String> = steps(&db_job())
+            .iter()
+            .map(|s| {
+                format!(
+                    "{} {} {} {} {}",
+                    s.id, s.state, s.nodes, s.elapsed, s.command
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                "1003.batch COMPLETED cpu01 1:02:03 batch",
+                "1003.0 COMPLETED cpu01 1:01:40 preprocess",
+            ]
+        );
+    }
+
+    #[test]
+    fn usage_from_slurmdb() {
+        let usage = usage(&db_job());
+        let step = &usage[1];
+        assert_eq!(step.id, "1003.0");
+        assert_eq!(step.ave_cpu, "1:56:40");
+        assert_eq!(step.max_rss, "24.0G");
+        assert_eq!(step.ave_rss, "20.0G");
+        assert_eq!(step.max_disk_read, "4.0G");
+        assert_eq!(step.max_disk_write, "2.0G");
+        assert_eq!(step.tres_usage, "cpu=1:56:40,mem=20.0G,fs/disk=4.0G");
+    }
+
+    #[test]
+    fn efficiency_from_slurmdb() {
+        let e = efficiency(&db_job());
+        assert_eq!(e.cpu_used, TimeDelta::seconds(7300));
+        assert_eq!(e.cpu_allocated, TimeDelta::seconds(3723 * 8));
+        assert_eq!(e.mem_used, 24 << 30);
+        assert_eq!(e.mem_requested, 32 << 30);
+    }
+
+    #[test]
+    fn old_step_ids() {
+        let step = json!({"step": {"id": {"job_id": 12, "step_id": "batch"}}});
+        assert_eq!(step_id(&step), "12.batch");
+    }
+
+    #[test]
+    fn partitions_from_nodes() {
+        let partitions = partitions(
+            &serde_json::from_str(include_str!("../tests/fixtures/slurmrestd/partitions.json"))
+                .unwrap(),
+            &serde_json::from_str(include_str!("../tests/fixtures/slurmrestd/nodes.json")).unwrap(),
+        );
+        let counts = |c: Counts| format!("{}/{}/{}/{}", c.allocated, c.idle, c.other, c.total);
+        let partitions: Vec<String> = partitions
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} {} {} {}",
+                    p.name,
+                    p.state,
+                    counts(p.nodes),
+                    counts(p.cpus)
+                )
+            })
+            .collect();
+        assert_eq!(
+            partitions,
+            ["gpu* up 2/1/1/4 36/36/24/96", "debug up 1/0/1/2 4/20/24/48"]
+        );
+    }
+
+    #[test]
+    fn overallocated_node() {
+        let partitions = partitions(
+            &json!({"partitions": [{"name": "gpu", "partition": {"state": ["UP"]}}]}),
+            &json!({"nodes": [
+                {"partitions": ["gpu"], "state": ["MIXED"], "cpus": 8, "alloc_cpus": 10}
+            ]}),
+        );
+        assert_eq!(partitions[0].cpus.allocated, 10);
+        assert_eq!(partitions[0].cpus.idle, 0);
+    }
+}
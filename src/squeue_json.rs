@@ -0,0 +1,291 @@
+use chrono::{DateTime, Local, TimeDelta};
+use serde_json::Value;
+
+use crate::jobs::{compact_state, parse_paths, Job};
+use crate::time::format_duration;
+
+// Since Slurm 21.08, `squeue --json` gives the same information as the
+// --Format fields, without any separator which could turn up in a job
+// name. Its layout has changed between versions (e.g. numbers became
+// {"set": true, "infinite": false, "number": 5} objects and job_state
+// became a list in 23.02), so both old and new forms are accepted.
+pub fn parse_jobs(json: &str) -> Result<Vec<Job>, String> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| format!("invalid squeue JSON: {}", e))?;
+    jobs_from_json(&value)
+}
+
+// slurmrestd answers with the same {"jobs": [...]} as squeue --json
+pub fn jobs_from_json(value: &Value) -> Result<Vec<Job>, String> {
+    let jobs = value
+        .get("jobs")
+        .and_then(|jobs| jobs.as_array())
+        .ok_or("squeue JSON has no jobs list")?;
+    Ok(jobs.iter().flat_map(parse_job).collect())
+}
+
+// squeue --json ignores --array, so pending array tasks which have not
+// been split off yet come as one job, with a range like 0-99%10. Like
+// the text output, they are turned into one job per task, and tasks
+// are known by their array's ID and their own, e.g. 1234_5.
+fn parse_job(json: &Value) -> Vec<Job> {
+    let job_id = text(json, "job_id");
+    let state = match json.get("job_state") {
+        Some(Value::Array(states)) => states.first().map(value_text).unwrap_or_default(),
+        _ => text(json, "job_state"),
+    };
+    let array_job_id = match number(json, "array_job_id") {
+        Some(id) if id > 0 => id.to_string(),
+        _ => job_id.clone(),
+    };
+    let array_task_ids = match number(json, "array_task_id") {
+        Some(task) => vec![task.to_string()],
+        None => match text(json, "array_task_string").as_str() {
+            "" => vec!["N/A".to_string()],
+            tasks => expand_task_ids(tasks),
+        },
+    };
+    let start_time = number(json, "start_time").filter(|&t| t > 0);
+    let time_used = match (state.as_str(), start_time) {
+        ("PENDING", _) | (_, None) => TimeDelta::zero(),
+        (_, Some(start)) => {
+            let end = number(json, "end_time")
+                .filter(|&t| t > 0 && !matches!(state.as_str(), "RUNNING" | "SUSPENDED"))
+                .unwrap_or(Local::now().timestamp());
+            let suspended = number(json, "suspend_time").unwrap_or(0);
+            TimeDelta::seconds((end - start - suspended).max(0))
+        }
+    };
+    let time_limit = match number(json, "time_limit") {
+        Some(minutes) => format_duration(TimeDelta::minutes(minutes)),
+        None => "UNLIMITED".to_string(),
+    };
+    let tres = match text(json, "tres_alloc_str").as_str() {
+        "" => text(json, "tres_req_str"),
+        tres => tres.to_string(),
+    };
+    let num_tasks = ["tasks", "num_tasks", "number_of_tasks"]
+        .iter()
+        .find_map(|key| number(json, key))
+        .map_or(String::new(), |n| n.to_string());
+
+    let job = Job {
+        StateCompact: compact_state(&state).to_string(),
+        State: state,
+        Reason: text(json, "state_reason"),
+        Name: text(json, "name"),
+        UserName: text(json, "user_name"),
+        Account: text(json, "account"),
+        QOS: text(json, "qos"),
+        Cluster: text(json, "cluster"),
+        JobID: job_id,
+        ArrayJobID: array_job_id,
+        ArrayTaskID: String::new(),
+        Partition: text(json, "partition"),
+        NodeList: text(json, "nodes"),
+        ReqNodes: text(json, "required_nodes"),
+        ExcNodes: text(json, "excluded_nodes"),
+        SubmitTime: timestamp(number(json, "submit_time")),
+        StartTime: timestamp(start_time),
+        TimeLimit: time_limit,
+        TimeUsed: format_duration(time_used),
+        TRES: tres,
+        Dependency: text(json, "dependency"),
+        NumTasks: num_tasks,
+        Priority: text(json, "priority"),
+        Nice: text(json, "nice"),
+        WorkDir: text(json, "current_working_directory"),
+        Command: text(json, "command"),
+        STDOUT: text(json, "standard_output"),
+        STDERR: text(json, "standard_error"),
+    };
+    array_task_ids
+        .into_iter()
+        .map(|task| {
+            let job_id = match task.as_str() {
+                "N/A" => job.JobID.clone(),
+                task => format!("{}_{}", job.ArrayJobID, task),
+            };
+            let mut job = Job {
+                JobID: job_id,
+                ArrayTaskID: task,
+                ..job.clone()
+            };
+            parse_paths(&mut job);
+            job
+        })
+        .collect()
+}
+
+// A number, which from 23.02 onwards is wrapped in an object saying
+// whether it is set at all, or infinite. Either of those gives None.
+fn number(json: &Value, key: &str) -> Option<i64> {
+    match json.get(key)? {
+        Value::Number(n) => n.as_i64(),
+        Value::Object(o) => match (o.get("set"), o.get("infinite")) {
+            (Some(Value::Bool(false)), _) | (_, Some(Value::Bool(true))) => None,
+            _ => o.get("number")?.as_i64(),
+        },
+        _ => None,
+    }
+}
+
+fn text(json: &Value, key: &str) -> String {
+    json.get(key).map(value_text).unwrap_or_default()
+}
+
+pub fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Object(o) => match o.get("set") {
+            Some(Value::Bool(false)) => String::new(),
+            _ => o.get("number").map(value_text).unwrap_or_default(),
+        },
+        _ => String::new(),
+    }
+}
+
+// As squeue prints them, in local time, or N/A when not known
+fn timestamp(seconds: Option<i64>) -> String {
+    seconds
+        .filter(|&s| s > 0)
+        .and_then(|s| DateTime::from_timestamp(s, 0))
+        .map_or("N/A".to_string(), |t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string()
+        })
+}
+
+// e.g. 1,3,5-9:2%4 -> 1, 3, 5, 7, 9. The %4 (how many tasks may run at
+// once) does not matter here.
+fn expand_task_ids(tasks: &str) -> Vec<String> {
+    let tasks = tasks.split('%').next().unwrap_or_default();
+    let mut ids = vec![];
+    for part in tasks.split(',') {
+        let (range, step) = match part.split_once(':') {
+            Some((range, step)) => (range, step.parse().unwrap_or(1).max(1)),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (start.parse::<u64>(), end.parse::<u64>()),
+            None => (range.parse(), range.parse()),
+        };
+        match (start, end) {
+            (Ok(start), Ok(end)) => ids.extend((start..=end).step_by(step).map(|i| i.to_string())),
+            _ => ids.push(part.to_string()),
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same queue as squeue printed it before and after 23.02: a
+    // running job, one pending on a dependency, a completed one, and
+    // an array with task 0 running and tasks 1, 3 and 5 pending.
+    const BEFORE_23_02: &str = include_str!("../tests/fixtures/squeue-22.05.json");
+    const SINCE_23_02: &str = include_str!("../tests/fixtures/squeue-23.02.json");
+
+    fn find<'a>(jobs: &'a [Job], job_id: &str) -> &'a Job {
+        jobs.iter().find(|j| j.JobID == job_id).unwrap()
+    }
+
+    fn check_fields(jobs: &[Job]) {
+        let running = find(jobs, "1001");
+        assert_eq!(running.State, "RUNNING");
+        assert_eq!(running.StateCompact, "R");
+        assert_eq!(running.Reason, "None");
+        assert_eq!(running.Name, "train");
+        assert_eq!(running.UserName, "alice");
+        assert_eq!(running.Account, "research");
+        assert_eq!(running.QOS, "normal");
+        assert_eq!(running.Cluster, "cluster");
+        assert_eq!(running.ArrayJobID, "1001");
+        assert_eq!(running.ArrayTaskID, "N/A");
+        assert_eq!(running.Partition, "gpu");
+        assert_eq!(running.NodeList, "gpu01");
+        assert_eq!(running.TimeLimit, "4:00:00");
+        assert_eq!(
+            running.TRES,
+            "cpu=8,mem=32G,node=1,billing=8,gres/gpu:a100=2"
+        );
+        assert_eq!(running.NumTasks, "1");
+        assert_eq!(running.Priority, "10000");
+        assert_eq!(running.Nice, "0");
+        assert_eq!(running.WorkDir, "/home/alice");
+        assert_eq!(running.Command, "/home/alice/run.sh");
+        assert_eq!(running.STDOUT, "/home/alice/slurm-1001.out");
+        assert_ne!(running.StartTime, "N/A");
+
+        // nothing allocated yet, so TRES is what was asked for
+        let pending = find(jobs, "1002");
+        assert_eq!(pending.StateCompact, "PD");
+        assert_eq!(pending.Reason, "Dependency");
+        assert_eq!(pending.Dependency, "afterok:1001(unfulfilled)");
+        assert_eq!(pending.StartTime, "N/A");
+        assert_eq!(pending.TimeUsed, "0:00");
+        assert_eq!(
+            pending.TRES,
+            "cpu=8,mem=32G,node=1,billing=8,gres/gpu:a100=2"
+        );
+
+        let completed = find(jobs, "1003");
+        assert_eq!(completed.StateCompact, "CD");
+        assert_eq!(completed.TimeUsed, "1:02:03");
+    }
+
+    #[test]
+    fn fields_before_23_02() {
+        let jobs = parse_jobs(BEFORE_23_02).unwrap();
+        check_fields(&jobs);
+        assert_eq!(find(&jobs, "1002").TimeLimit, "1:00:00");
+    }
+
+    #[test]
+    fn fields_since_23_02() {
+        let jobs = parse_jobs(SINCE_23_02).unwrap();
+        check_fields(&jobs);
+        assert_eq!(find(&jobs, "1002").TimeLimit, "UNLIMITED");
+    }
+
+    #[test]
+    fn arrays() {
+        for json in [BEFORE_23_02, SINCE_23_02] {
+            let jobs = parse_jobs(json).unwrap();
+            let tasks: Vec<String> = jobs
+                .iter()
+                .filter(|j| j.ArrayJobID == "1010")
+                .map(|j| format!("{} {} {} {}", j.JobID, j.ArrayTaskID, j.State, j.STDOUT))
+                .collect();
+            assert_eq!(
+                tasks,
+                [
+                    "1010_0 0 RUNNING /home/dave/slurm-1010_0.out",
+                    "1010_1 1 PENDING /home/dave/slurm-1010_1.out",
+                    "1010_3 3 PENDING /home/dave/slurm-1010_3.out",
+                    "1010_5 5 PENDING /home/dave/slurm-1010_5.out",
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn task_ids() {
+        assert_eq!(expand_task_ids("7"), ["7"]);
+        assert_eq!(expand_task_ids("0-3"), ["0", "1", "2", "3"]);
+        assert_eq!(expand_task_ids("1,3,5-9:2%4"), ["1", "3", "5", "7", "9"]);
+        // anything else is kept as it is
+        assert_eq!(expand_task_ids("2,x-y"), ["2", "x-y"]);
+    }
+
+    #[test]
+    fn not_json() {
+        assert!(parse_jobs("squeue: error: Invalid user").is_err());
+        assert!(parse_jobs("{}").is_err());
+    }
+}
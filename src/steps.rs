@@ -0,0 +1,98 @@
+use crate::command::Runner;
+
+// A job step: the batch script, the extern step, or anything launched
+// with srun inside the job's allocation.
+pub struct Step {
+    pub id: String,
+    pub state: String,
+    pub nodes: String,
+    pub elapsed: String,
+    pub command: String,
+}
+
+// Steps of jobs which are still running are listed by squeue, but
+// once a job is done we have to ask the accounting database instead.
+pub fn get_steps(runner: &Runner, job_id: &str, live: bool) -> Result<Vec<Step>, String> {
+    if live {
+        get_live_steps(runner, job_id)
+    } else {
+        get_finished_steps(runner, job_id)
+    }
+}
+
+// squeue doesn't know the command a step is running, but the name of
+// a step defaults to the program that srun launched.
+fn get_live_steps(runner: &Runner, job_id: &str) -> Result<Vec<Step>, String> {
+    let output_separator = "###";
+    let format_str = ["StepId", "StepState", "NodeList", "TimeUsed", "StepName"]
+        .map(|f| format!("{}:{}", f, output_separator))
+        .join(",");
+    let output = runner.run(
+        "squeue",
+        &[
+            "--steps",
+            "--noheader",
+            "--jobs",
+            job_id,
+            "--Format",
+            &format_str,
+        ],
+    )?;
+
+    let steps = output
+        .lines()
+        .filter_map(|l| {
+            let parts: Vec<&str> = l.trim().split(output_separator).collect();
+            match parts[..] {
+                [id, state, nodes, elapsed, name, _] => Some(Step {
+                    id: id.to_string(),
+                    state: state.to_string(),
+                    nodes: nodes.to_string(),
+                    elapsed: elapsed.to_string(),
+                    command: name.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(steps)
+}
+
+fn get_finished_steps(runner: &Runner, job_id: &str) -> Result<Vec<Step>, String> {
+    // SubmitLine is only known to Slurm >= 23.02, older versions
+    // have to make do with the name of the step instead.
+    let format = "JobID,State,NodeList,Elapsed,SubmitLine";
+    let args = |format| {
+        [
+            "--noheader",
+            "--parsable2",
+            "--jobs",
+            job_id,
+            "--format",
+            format,
+        ]
+    };
+    let output = runner
+        .run("sacct", &args(format))
+        .or_else(|_| runner.run("sacct", &args("JobID,State,NodeList,Elapsed,JobName")))?;
+
+    let steps = output
+        .lines()
+        .filter_map(|l| {
+            let parts: Vec<&str> = l.split('|').collect();
+            match parts[..] {
+                // the job itself is listed too, we only want its steps
+                [id, ..] if !id.contains('.') => None,
+                [id, state, nodes, elapsed, command] => Some(Step {
+                    id: id.to_string(),
+                    state: state.to_string(),
+                    nodes: nodes.to_string(),
+                    elapsed: elapsed.to_string(),
+                    command: command.to_string(),
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(steps)
+}
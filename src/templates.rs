@@ -0,0 +1,126 @@
+use regex::{Captures, Regex};
+use std::{fs, sync::LazyLock};
+
+use crate::config::config_dir;
+
+// Batch scripts to submit new jobs from, stored one per file in
+// $XDG_CONFIG_HOME/lazyslurm/templates/ and named after the file, e.g.
+// "8xGPU training.sh". Templates can contain placeholders, optionally
+// with a default value, which are filled in before submitting:
+//
+//     #!/bin/bash
+//     #SBATCH --job-name={{name}}
+//     #SBATCH --time={{time=1:00:00}}
+//     #SBATCH --gres=gpu:a100:{{gpus=1}}
+//     {{command}}
+//
+#[derive(Clone)]
+pub struct Template {
+    pub name: String,
+    pub script: String,
+}
+
+static PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{(\w+)(?:=([^}]*))?\}\}").unwrap());
+
+pub fn load_templates() -> Result<Vec<Template>, String> {
+    let Some(dir) = config_dir().map(|dir| dir.join("templates")) else {
+        return Ok(vec![]);
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(vec![]);
+    };
+    let mut templates = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let script = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        templates.push(Template { name, script });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+impl Template {
+    // The placeholders in the template and their defaults, in the order
+    // they first appear. A placeholder used more than once is only
+    // asked for once.
+    pub fn placeholders(&self) -> Vec<(String, String)> {
+        let mut placeholders: Vec<(String, String)> = vec![];
+        for caps in PLACEHOLDER_RE.captures_iter(&self.script) {
+            if placeholders.iter().any(|(name, _)| name == &caps[1]) {
+                continue;
+            }
+            let default = caps.get(2).map_or("", |m| m.as_str());
+            placeholders.push((caps[1].to_string(), default.to_string()));
+        }
+        placeholders
+    }
+
+    pub fn fill(&self, values: &[(String, String)]) -> String {
+        PLACEHOLDER_RE
+            .replace_all(&self.script, |caps: &Captures| {
+                values
+                    .iter()
+                    .find(|(name, _)| name == &caps[1])
+                    .map_or(String::new(), |(_, value)| value.clone())
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(script: &str) -> Template {
+        Template {
+            name: "test".to_string(),
+            script: script.to_string(),
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let template = template(
+            "#SBATCH --job-name={{name}}\n\
+             #SBATCH --time={{time=1:00:00}}\n\
+             #SBATCH --output={{name}}.out\n\
+             #SBATCH --comment={{note=}}\n\
+             echo {{ not a placeholder }} {{}}\n",
+        );
+        let placeholders = template.placeholders();
+        let placeholders: Vec<(&str, &str)> = placeholders
+            .iter()
+            .map(|(name, default)| (name.as_str(), default.as_str()))
+            .collect();
+        // each asked for once, in order, and only when well formed
+        assert_eq!(
+            placeholders,
+            [("name", ""), ("time", "1:00:00"), ("note", "")]
+        );
+    }
+
+    #[test]
+    fn fill() {
+        let template =
+            template("--job-name={{name}} --time={{time=1:00:00}} {{name}}.out {{gpus}}");
+        let values = [
+            ("name".to_string(), "train".to_string()),
+            ("time".to_string(), "2:00:00".to_string()),
+        ];
+        // anything not given a value is left empty
+        assert_eq!(
+            template.fill(&values),
+            "--job-name=train --time=2:00:00 train.out "
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{Local, NaiveDateTime, TimeDelta};
+
+// squeue prints timestamps in local time, e.g. 2024-06-01T12:30:00.
+// Fields which are not set come out as N/A, Unknown or None.
+pub fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+// A rough, human readable version of how far away a time is,
+// e.g. "in ~3h" or "~20m ago".
+pub fn relative_to_now(time: NaiveDateTime) -> String {
+    let delta = time - Local::now().naive_local();
+    if delta.abs() < TimeDelta::minutes(1) {
+        return "now".to_string();
+    }
+    let approx = format_approx(delta.abs());
+    if delta > TimeDelta::zero() {
+        format!("in ~{}", approx)
+    } else {
+        format!("~{} ago", approx)
+    }
+}
+
+fn format_approx(delta: TimeDelta) -> String {
+    if delta >= TimeDelta::days(2) {
+        format!("{}d", delta.num_days())
+    } else if delta >= TimeDelta::hours(1) {
+        format!("{}h", delta.num_hours())
+    } else {
+        format!("{}m", delta.num_minutes())
+    }
+}
+
+// Durations in Slurm look like [days-]hours:minutes:seconds, with
+// the leading parts left out when they are zero, e.g. 1-02:03:04,
+// 2:03:04 or 3:04. Limits can also be UNLIMITED, NOT_SET etc.
+pub fn parse_duration(duration: &str) -> Option<TimeDelta> {
+    let (days, rest) = match duration.split_once('-') {
+        Some((d, rest)) => (d.parse::<i64>().ok()?, rest),
+        None => (0, duration),
+    };
+    let parts = rest
+        .split(':')
+        .map(|p| p.parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()?;
+    let (hours, minutes, seconds) = match (duration.contains('-'), &parts[..]) {
+        (_, &[h, m, s]) => (h, m, s),
+        // with days, the time is hours[:minutes], otherwise minutes[:seconds]
+        (true, &[h, m]) => (h, m, 0),
+        (true, &[h]) => (h, 0, 0),
+        (false, &[m, s]) => (0, m, s),
+        (false, &[m]) => (0, m, 0),
+        _ => return None,
+    };
+    Some(
+        TimeDelta::days(days)
+            + TimeDelta::hours(hours)
+            + TimeDelta::minutes(minutes)
+            + TimeDelta::seconds(seconds),
+    )
+}
+
+// The inverse of parse_duration
+pub fn format_duration(duration: TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    let seconds = duration.num_seconds() % 60;
+    match (days, hours) {
+        (0, 0) => format!("{}:{:02}", minutes, seconds),
+        (0, _) => format!("{}:{:02}:{:02}", hours, minutes, seconds),
+        _ => format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds),
+    }
+}
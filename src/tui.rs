@@ -15,7 +15,7 @@ impl Tui {
         Self { terminal }
     }
 
-    pub fn enter(&self) -> () {
+    pub fn enter(&self) {
         crossterm::terminal::enable_raw_mode().unwrap();
         crossterm::execute!(
             std::io::stderr(),
@@ -26,7 +26,7 @@ impl Tui {
         .unwrap();
     }
 
-    pub fn exit(&self) -> () {
+    pub fn exit(&self) {
         crossterm::execute!(
             std::io::stderr(),
             LeaveAlternateScreen,
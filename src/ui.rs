@@ -3,22 +3,69 @@ use ratatui::{
     style::{Color, Modifier, Style},
     terminal::Frame,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Table,
+        TableState, Tabs, Wrap,
+    },
 };
 
-use crate::app::{App, EditorState, ViewState};
-use crate::jobs::{ClusterOverview, Job};
+use chrono::TimeDelta;
+use std::collections::HashMap;
 
-const HELP_SHORT: &str = "q: quit | ?: toggle help | o: toggle overview | <tab>: toggle focus";
-const HELP: &str = "lazyslurm is for monitoring SLURM jobs.
+use crate::app::{App, EditorState, JobRow, ViewState};
+use crate::config::{config_dir, Config, Theme};
+use crate::deps::{get_downstream, get_upstream, refers_to, DependencyNode, DependencyStatus};
+use crate::form::Form;
+use crate::jobs::{
+    compact_state, get_array_stats, get_time_remaining, ArrayStats, ClusterOverview, GroupBy, Job,
+    ReasonStats, StartEstimate,
+};
+use crate::partitions::{Counts, Partition};
+use crate::steps::Step;
+use crate::templates::Template;
+use crate::time::{format_duration, relative_to_now};
+use crate::usage::{format_mem, get_mem_per_node, parse_mem, Efficiency, StepUsage};
+
+const HELP_SHORT: &str =
+    "q: quit | ?: toggle help | o: toggle overview | b: group by | <tab>: toggle focus";
+pub const HELP: &str = "lazyslurm is for monitoring SLURM jobs.
 
 ## Keymaps
 
 q | Ctrl-c           : quit
 ?                    : toggle help
 o                    : toggle cluster overview
+b                    : cycle overview grouping (user, partition,
+                       account, QOS, node, GPU type, cluster)
+c                    : switch between configured clusters (all
+                       of them together, or one at a time)
+r                    : toggle pending reasons
+p                    : toggle partitions and how busy their nodes are
+f                    : show/hide jobs which have recently ended
+<space>              : expand/collapse job array
+s                    : toggle steps of the selected job
+u                    : toggle resource usage of the selected job
+d                    : toggle dependencies of the selected job
+i                    : toggle all details of the selected job
+                       (scroll with j/k)
+B                    : toggle batch script of the selected job
+                       (scroll with j/k)
+l / L                : follow the output / errors of the selected job
+                       (scroll with j/k)
+e                    : edit the selected pending job (or array)
+R                    : resubmit the selected job, optionally with
+                       different options
+K                    : cancel the selected job (or array)
+C                    : cancel all of your jobs matching the filter
+                       (asks for the number of jobs to be typed in)
+h / H                : hold / release the selected pending job (or
+                       array), once confirmed
+n                    : submit a new job from a template
+x / X                : export the filtered jobs (or the overview, when
+                       shown) to a CSV / JSON file
+<enter>              : show jobs pending for the selected reason
 <tab>                : toggle focus
-<esc>                : reset view
+<esc>                : reset view (and clear the reason filter)
 
 j | <Down arrow key> : next row
 k | <Up arrow key>   : previous row
@@ -27,6 +74,35 @@ g | Home             : go to first row
 Ctrl-d | PageDown    : down 5 rows
 Ctrl-u | PageUp      : up 5 rows
 
+## Pending jobs
+
+Instead of the time used, pending jobs show when the
+scheduler expects them to start. This is only an
+estimate: it is shown in red once it has slipped.
+
+## Running jobs
+
+Running jobs which are close to their time limit are
+shown in bold red. The warning threshold can be set
+with timeout_warning_minutes in
+~/.config/lazyslurm/config.toml (default 30).
+
+## Finished jobs
+
+The Details panel of finished jobs shows how much of
+their CPU time and memory they actually used. When
+grouped by user, the overview also shows this for
+all jobs which finished in the last day. Values
+below efficiency_warning_percent (default 50) are
+shown in red.
+
+## Job states
+
+Job states are colour coded: running jobs are green,
+pending jobs yellow, completing jobs cyan, failed
+jobs red and suspended jobs grey. The colours can be
+changed in the [theme.states] table of config.toml.
+
 ## Filtering jobs
 
 The live filter box accepts arbitrary regex which
@@ -51,125 +127,967 @@ The matching order will be the same as the ordering
 shown in the Details panel.
 ";
 
-fn get_short_jobs_list(jobs: &Vec<Job>) -> Vec<ListItem> {
-    jobs.iter()
-        .map(|j| {
-            ListItem::new(Line::from(vec![
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.StateCompact, max = 2),
-                    Style::default(),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.JobID, max = 10),
-                    Style::default().fg(Color::Yellow),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.UserName, max = 10),
-                    Style::default().fg(Color::Blue),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.TimeUsed, max = 11),
-                    Style::default().fg(Color::Cyan),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.NodeList, max = 10),
-                    Style::default().fg(Color::Magenta),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$} ", j.Partition, max = 10),
-                    Style::default().fg(Color::Green),
-                ),
-                Span::styled(
-                    format!(" {:<max$.max$}", j.Name, max = 100),
-                    Style::default().fg(Color::LightRed),
-                ),
-            ]))
+fn get_job_line<'a>(
+    j: &'a Job,
+    start_estimates: &HashMap<(String, String), StartEstimate>,
+    config: &Config,
+    show_cluster: bool,
+) -> Line<'a> {
+    // pending jobs have not used any time yet, so show
+    // when they are expected to start instead
+    let key = (j.Cluster.clone(), j.JobID.clone());
+    let (time, time_style) = match start_estimates.get(&key) {
+        Some(e) if j.State == "PENDING" => (
+            relative_to_now(e.start),
+            match e.slipped {
+                0 => Style::default().fg(Color::Cyan),
+                _ => Style::default().fg(Color::LightRed),
+            },
+        ),
+        _ => (j.TimeUsed.clone(), Style::default().fg(Color::Cyan)),
+    };
+    let mut line = Line::from(vec![
+        Span::styled(
+            format!(" {:<max$.max$} ", j.StateCompact, max = 2),
+            config.theme.state_style(&j.StateCompact),
+        ),
+        get_cluster_span(&j.Cluster, show_cluster),
+        Span::styled(
+            format!(" {:<max$.max$} ", j.JobID, max = 10),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::styled(
+            format!(" {:<max$.max$} ", j.UserName, max = 10),
+            Style::default().fg(Color::Blue),
+        ),
+        Span::styled(format!(" {:<max$.max$} ", time, max = 11), time_style),
+        Span::styled(
+            format!(" {:<max$.max$} ", j.NodeList, max = 10),
+            Style::default().fg(Color::Magenta),
+        ),
+        Span::styled(
+            format!(" {:<max$.max$} ", j.Partition, max = 10),
+            Style::default().fg(Color::Green),
+        ),
+        Span::styled(
+            format!(" {:<max$.max$}", j.Name, max = 100),
+            Style::default().fg(Color::LightRed),
+        ),
+    ]);
+
+    // make jobs which are about to time out stand out
+    if let Some((remaining, _)) = get_time_remaining(j) {
+        if remaining <= config.timeout_warning {
+            let warning = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+            for span in line.spans.iter_mut() {
+                span.style = span.style.patch(warning);
+            }
+        }
+    }
+    line
+}
+
+fn describe_array_stats(stats: &ArrayStats) -> String {
+    format!(
+        "{} R / {} PD / {} done, {} GPUs",
+        stats.running_tasks, stats.pending_tasks, stats.done_tasks, stats.gpus_used
+    )
+}
+
+// Only shown when jobs from several clusters are listed together.
+fn get_cluster_span(cluster: &str, show_cluster: bool) -> Span<'_> {
+    match show_cluster {
+        true => Span::styled(
+            format!(" {:<max$.max$} ", cluster, max = 10),
+            Style::default().fg(Color::LightBlue),
+        ),
+        false => Span::raw(""),
+    }
+}
+
+fn get_array_line<'a>(
+    id: &'a str,
+    tasks: &[&'a Job],
+    expanded: bool,
+    show_cluster: bool,
+) -> Line<'a> {
+    let first = tasks[0];
+    let stats = get_array_stats(tasks.iter().copied());
+    Line::from(vec![
+        Span::styled(
+            format!(" {:<max$.max$} ", if expanded { "-" } else { "+" }, max = 2),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        get_cluster_span(&first.Cluster, show_cluster),
+        Span::styled(
+            format!(" {:<max$.max$} ", id, max = 10),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::styled(
+            format!(" {:<max$.max$} ", first.UserName, max = 10),
+            Style::default().fg(Color::Blue),
+        ),
+        Span::styled(
+            format!(
+                " {:<max$.max$} ",
+                format!("{} tasks", tasks.len()),
+                max = 11
+            ),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::styled(format!(" {:<max$.max$} ", "", max = 10), Style::default()),
+        Span::styled(
+            format!(" {:<max$.max$} ", first.Partition, max = 10),
+            Style::default().fg(Color::Green),
+        ),
+        Span::styled(
+            format!(
+                " {:<max$.max$}",
+                format!("{} ({})", first.Name, describe_array_stats(&stats)),
+                max = 100
+            ),
+            Style::default().fg(Color::LightRed),
+        ),
+    ])
+}
+
+fn get_short_jobs_list<'a>(
+    rows: &'a [JobRow],
+    jobs: &'a [Job],
+    start_estimates: &HashMap<(String, String), StartEstimate>,
+    config: &Config,
+    show_cluster: bool,
+) -> Vec<ListItem<'a>> {
+    rows.iter()
+        .map(|row| match row {
+            JobRow::Job(i) | JobRow::ArrayTask(i) => ListItem::new(get_job_line(
+                &jobs[*i],
+                start_estimates,
+                config,
+                show_cluster,
+            )),
+            JobRow::Array {
+                id,
+                tasks,
+                expanded,
+            } => {
+                let tasks: Vec<&Job> = tasks.iter().map(|t| &jobs[*t]).collect();
+                ListItem::new(get_array_line(id, &tasks, *expanded, show_cluster))
+            }
         })
         .collect()
 }
 
-fn style_job_field<'a>(field: String, value: String, max_width: usize) -> Line<'a> {
+fn style_job_field<'a>(field: String, value: String, max_width: usize, style: Style) -> Line<'a> {
     Line::from(vec![
         Span::styled(
             format!("{:<max$.max$}", { field }, max = max_width),
             Style::default().fg(Color::Yellow),
         ),
         Span::raw(" "),
-        Span::styled(value, Style::default()),
+        Span::styled(value, style),
     ])
 }
 
-fn get_job_details(job: &Job) -> Paragraph {
+fn describe_start_estimate(estimate: &StartEstimate) -> String {
+    match estimate.slipped {
+        0 => format!("expected {}", relative_to_now(estimate.start)),
+        1 => format!(
+            "expected {}, estimate has slipped once",
+            relative_to_now(estimate.start)
+        ),
+        n => format!(
+            "expected {}, estimate has slipped {} times",
+            relative_to_now(estimate.start),
+            n
+        ),
+    }
+}
+
+fn style_efficiency(percent: Option<f64>, threshold: f64) -> (String, Style) {
+    match percent {
+        Some(p) if p < threshold => (format!("{:.0}%", p), Style::default().fg(Color::Red)),
+        Some(p) => (format!("{:.0}%", p), Style::default().fg(Color::Green)),
+        None => ("-".to_string(), Style::default()),
+    }
+}
+
+fn get_efficiency_lines<'a>(
+    efficiency: &Result<Efficiency, String>,
+    threshold: f64,
+    max_width: usize,
+) -> Vec<Line<'a>> {
+    let e = match efficiency {
+        Ok(e) => e,
+        Err(e) => {
+            return vec![style_job_field(
+                "Efficiency".to_string(),
+                e.clone(),
+                max_width,
+                Style::default(),
+            )]
+        }
+    };
+    let (cpu, cpu_style) = style_efficiency(e.cpu_percent(), threshold);
+    let (mem, mem_style) = style_efficiency(e.mem_percent(), threshold);
+    vec![
+        style_job_field(
+            "CPU eff".to_string(),
+            format!(
+                "{} ({} of {} core time)",
+                cpu,
+                format_duration(e.cpu_used),
+                format_duration(e.cpu_allocated)
+            ),
+            max_width,
+            cpu_style,
+        ),
+        style_job_field(
+            "Memory eff".to_string(),
+            format!(
+                "{} ({} of {} per node)",
+                mem,
+                format_mem(e.mem_used),
+                format_mem(e.mem_requested)
+            ),
+            max_width,
+            mem_style,
+        ),
+    ]
+}
+
+fn get_job_details<'a>(
+    job: &'a Job,
+    start_estimate: Option<&StartEstimate>,
+    efficiency: Option<&Result<Efficiency, String>>,
+    config: &Config,
+) -> Paragraph<'a> {
+    let theme = &config.theme;
     let max_width = 12;
     let fields = Job::field_names();
-    let values = Job::field_values(job);
+    let mut values = Job::field_values(job);
+
+    if let (Some(estimate), "PENDING") = (start_estimate, job.State.as_str()) {
+        let i = fields.iter().position(|f| *f == "StartTime").unwrap();
+        values[i] = format!("{} ({})", values[i], describe_start_estimate(estimate));
+    }
 
-    let lines: Vec<Line> = fields
+    let mut lines: Vec<Line> = fields
         .iter()
         .zip(values.iter())
         .map(|(f, v)| {
+            let style = match *f {
+                "StateCompact" | "State" => theme.state_style(&job.StateCompact),
+                _ => Style::default(),
+            };
             style_job_field(
                 f.to_owned().to_string(),
                 v.to_owned().to_string(),
                 max_width,
+                style,
             )
         })
         .collect();
+    if let Some(efficiency) = efficiency {
+        lines.extend(get_efficiency_lines(
+            efficiency,
+            config.efficiency_warning,
+            max_width,
+        ));
+    }
     let text = Text::from(lines);
     Paragraph::new(text)
 }
 
-fn get_user_stats(f: &mut Frame, area: Rect, overview: &ClusterOverview) {
-    let header_cells = ["User", "Running", "Pending", "GPUs"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+fn draw_job_details(f: &mut Frame, area: Rect, job: &Job, app: &App) {
+    let block = Block::new().borders(Borders::ALL).title_top("Details");
+    let mut inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if let Some((remaining, limit)) = get_time_remaining(job) {
+        let details_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Fill(1),
+            ])
+            .split(inner);
+        f.render_widget(
+            get_time_limit_gauge(remaining, limit, app.config.timeout_warning),
+            details_layout[0],
+        );
+        inner = details_layout[2];
+    }
+    let key = (job.Cluster.clone(), job.JobID.clone());
+    f.render_widget(
+        get_job_details(
+            job,
+            app.start_estimates.get(&key),
+            app.efficiency.get(&key),
+            &app.config,
+        ),
+        inner,
+    );
+}
+
+fn get_array_details<'a>(id: &str, tasks: &[&'a Job]) -> Paragraph<'a> {
+    let max_width = 12;
+    let first = tasks[0];
+    let stats = get_array_stats(tasks.iter().copied());
+    let fields = [
+        ("ArrayJobID", id.to_string()),
+        ("Name", first.Name.clone()),
+        ("UserName", first.UserName.clone()),
+        ("Partition", first.Partition.clone()),
+        ("Tasks", tasks.len().to_string()),
+        ("Running", stats.running_tasks.to_string()),
+        ("Pending", stats.pending_tasks.to_string()),
+        ("Done", stats.done_tasks.to_string()),
+        ("GPUs", stats.gpus_used.to_string()),
+    ];
+    let mut lines: Vec<Line> = fields
+        .into_iter()
+        .map(|(f, v)| style_job_field(f.to_string(), v, max_width, Style::default()))
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(Line::raw("Press <space> to show the individual tasks."));
+    lines.push(Line::raw("Steps, usage, info, the script and"));
+    lines.push(Line::raw("resubmitting work on a single task."));
+    Paragraph::new(Text::from(lines))
+}
+
+fn get_time_limit_gauge<'a>(
+    remaining: TimeDelta,
+    limit: TimeDelta,
+    timeout_warning: TimeDelta,
+) -> Gauge<'a> {
+    let used = limit - remaining;
+    let ratio = match limit.num_seconds() {
+        0 => 1.0,
+        l => used.num_seconds() as f64 / l as f64,
+    };
+    let colour = if remaining <= timeout_warning {
+        Color::Red
+    } else {
+        Color::Green
+    };
+    Gauge::default()
+        .gauge_style(Style::default().fg(colour))
+        .ratio(ratio.clamp(0.0, 1.0))
+        .label(format!(
+            "{} left of {}",
+            format_duration(remaining),
+            format_duration(limit)
+        ))
+}
+
+fn get_group_stats(
+    f: &mut Frame,
+    area: Rect,
+    overview: &ClusterOverview,
+    config: &Config,
+    user_efficiency: &Result<HashMap<String, Efficiency>, String>,
+) {
+    let theme = &config.theme;
+    // efficiency of finished jobs is only known per user
+    let user_efficiency = match (overview.group_by, user_efficiency) {
+        (GroupBy::User, Ok(e)) => Some(e),
+        _ => None,
+    };
+    // in a narrow panel, shorter headers and then no efficiency,
+    // rather than headers cut off part way
+    let fits = |headers: &[&str]| {
+        8 + headers.iter().map(|h| h.len() + 1).sum::<usize>()
+            <= area.width.saturating_sub(2) as usize
+    };
+    let mut headers = vec!["Running", "Pending", "GPUs"];
+    if user_efficiency.is_some() {
+        headers.extend(["CPU eff", "Mem eff"]);
+    }
+    if !fits(&headers) {
+        headers = vec!["R", "PD", "GPUs"];
+        if user_efficiency.is_some() {
+            headers.extend(["CPU%", "Mem%"]);
+        }
+    }
+    if !fits(&headers) {
+        headers.truncate(3);
+    }
+    let user_efficiency = user_efficiency.filter(|_| headers.len() > 3);
+    let header_cells = std::iter::once(overview.group_by.label())
+        .chain(headers)
+        .map(|h| Cell::from(h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let total_gpus: u32 = overview.user_stats.iter().map(|s| s.gpus_used).sum();
+    let total_gpus: u32 = overview.group_stats.iter().map(|s| s.gpus_used).sum();
 
     let total_row = Row::new(vec![
         Cell::from("TOTAL").style(Style::default().add_modifier(Modifier::BOLD)),
         Cell::from(overview.jobs_running.to_string())
-            .style(Style::default().add_modifier(Modifier::BOLD)),
+            .style(theme.state_style("R").add_modifier(Modifier::BOLD)),
         Cell::from(overview.jobs_pending.to_string())
-            .style(Style::default().add_modifier(Modifier::BOLD)),
+            .style(theme.state_style("PD").add_modifier(Modifier::BOLD)),
         Cell::from(total_gpus.to_string()).style(Style::default().add_modifier(Modifier::BOLD)),
     ])
     .height(1)
     .bottom_margin(1);
 
     let mut rows: Vec<Row> = overview
-        .user_stats
+        .group_stats
         .iter()
         .map(|s| {
-            Row::new(vec![
+            let mut cells = vec![
                 Cell::from(s.name.as_str()),
-                Cell::from(s.running_jobs.to_string()),
-                Cell::from(s.pending_jobs.to_string()),
+                Cell::from(s.running_jobs.to_string()).style(theme.state_style("R")),
+                Cell::from(s.pending_jobs.to_string()).style(theme.state_style("PD")),
                 Cell::from(s.gpus_used.to_string()),
-            ])
+            ];
+            if let Some(user_efficiency) = user_efficiency {
+                let e = user_efficiency.get(&s.name);
+                for percent in [
+                    e.and_then(|e| e.cpu_percent()),
+                    e.and_then(|e| e.mem_percent()),
+                ] {
+                    let (text, style) = style_efficiency(percent, config.efficiency_warning);
+                    cells.push(Cell::from(text).style(style));
+                }
+            }
+            Row::new(cells)
         })
         .collect();
 
     rows.insert(0, total_row);
 
+    // the names get whatever the numbers leave, but never none
+    let mut widths = vec![
+        Constraint::Min(8),
+        Constraint::Max(8),
+        Constraint::Max(8),
+        Constraint::Max(8),
+    ];
+    if user_efficiency.is_some() {
+        widths.extend([Constraint::Max(8), Constraint::Max(8)]);
+    }
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Overview by {}", overview.group_by.label())),
+    );
+
+    f.render_widget(table, area);
+}
+
+fn get_reason_stats(f: &mut Frame, area: Rect, reasons: &[ReasonStats], state: &mut TableState) {
+    let header_cells = ["Reason", "Pending", "Users"]
+        .into_iter()
+        .map(|h| Cell::from(h).style(Style::default().fg(Color::Yellow)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let total_pending: u32 = reasons.iter().map(|r| r.pending_jobs).sum();
+
+    let rows: Vec<Row> = reasons
+        .iter()
+        .map(|r| {
+            let users: Vec<String> = r
+                .users
+                .iter()
+                .map(|(user, n)| format!("{} ({})", user, n))
+                .collect();
+            Row::new(vec![
+                Cell::from(r.reason.as_str()),
+                Cell::from(r.pending_jobs.to_string()),
+                Cell::from(users.join(", ")),
+            ])
+        })
+        .collect();
+
     let table = Table::new(
         rows,
-        vec![
-            Constraint::Fill(1),
-            Constraint::Max(8),
-            Constraint::Max(8),
-            Constraint::Max(8),
-        ],
+        vec![Constraint::Max(24), Constraint::Max(8), Constraint::Fill(1)],
     )
     .header(header)
+    .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .title("Overview"),
+            .title(format!("Pending reasons ({} jobs)", total_pending)),
     );
 
-    f.render_widget(table, area);
+    f.render_stateful_widget(table, area, state);
+}
+
+// Nodes and CPUs as sinfo counts them: allocated/idle/other/total,
+// where other is down, drained and the like
+fn get_partition_stats<'a>(partitions: &'a Result<Vec<Partition>, String>) -> Table<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Partitions")
+        .title_bottom("allocated/idle/other/total");
+    let partitions = match partitions {
+        Ok(partitions) => partitions,
+        Err(e) => {
+            let row = Row::new([Cell::from(e.as_str()).style(Style::default().fg(Color::Red))]);
+            return Table::new([row], [Constraint::Fill(1)]).block(block);
+        }
+    };
+    let counts = |c: &Counts| format!("{}/{}/{}/{}", c.allocated, c.idle, c.other, c.total);
+    // with several clusters, partitions are often named the same on each
+    let clusters = partitions.iter().any(|p| !p.cluster.is_empty());
+    let mut header = vec!["Partition", "State", "Nodes", "CPUs", "CPU load"];
+    if clusters {
+        header.insert(0, "Cluster");
+    }
+    let header = Row::new(
+        header
+            .into_iter()
+            .map(|h| Cell::from(h).style(Style::default().fg(Color::Yellow))),
+    )
+    .height(1)
+    .bottom_margin(1);
+    let rows: Vec<Row> = partitions
+        .iter()
+        .map(|p| {
+            let load = match p.cpus.total {
+                0 => String::new(),
+                total => format!("{}%", p.cpus.allocated * 100 / total),
+            };
+            let state_style = match p.state.as_str() {
+                "up" => Style::default(),
+                _ => Style::default().fg(Color::Red),
+            };
+            let mut cells = vec![
+                Cell::from(p.name.as_str()),
+                Cell::from(p.state.as_str()).style(state_style),
+                Cell::from(counts(&p.nodes)),
+                Cell::from(counts(&p.cpus)),
+                Cell::from(load),
+            ];
+            if clusters {
+                cells.insert(0, Cell::from(p.cluster.as_str()));
+            }
+            Row::new(cells)
+        })
+        .collect();
+    let mut widths = vec![
+        Constraint::Min(10),
+        Constraint::Length(6),
+        Constraint::Length(12),
+        Constraint::Length(16),
+        Constraint::Length(8),
+    ];
+    if clusters {
+        widths.insert(0, Constraint::Min(8));
+    }
+    Table::new(rows, widths).header(header).block(block)
+}
+
+fn get_templates(
+    f: &mut Frame,
+    area: Rect,
+    templates: &Result<Vec<Template>, String>,
+    state: &mut ListState,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Submit a job from a template")
+        .title_bottom("<enter>: fill in and submit");
+    let templates = match templates {
+        Ok(templates) if templates.is_empty() => {
+            let dir = config_dir().map_or("templates".to_string(), |dir| {
+                dir.join("templates").display().to_string()
+            });
+            f.render_widget(
+                Paragraph::new(format!(
+                    "No templates found. Save batch scripts in {} to submit new jobs from them.\n\n\
+                     Placeholders like {{{{name}}}} or {{{{time=1:00:00}}}} (with a default) \
+                     are filled in before the job is submitted.",
+                    dir
+                ))
+                .wrap(Wrap { trim: true })
+                .block(block),
+                area,
+            );
+            return;
+        }
+        Ok(templates) => templates,
+        Err(e) => {
+            f.render_widget(
+                Paragraph::new(e.as_str())
+                    .style(Style::default().fg(Color::Red))
+                    .wrap(Wrap { trim: true })
+                    .block(block),
+                area,
+            );
+            return;
+        }
+    };
+    let items: Vec<ListItem> = templates
+        .iter()
+        .map(|t| ListItem::new(t.name.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, state);
+}
+
+fn get_steps_details<'a>(
+    job_id: &str,
+    steps: &'a Result<Vec<Step>, String>,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Steps of {}", job_id));
+    let steps = match steps {
+        Ok(steps) if steps.is_empty() => {
+            return Paragraph::new("No steps found for this job.").block(block)
+        }
+        Ok(steps) => steps,
+        Err(e) => return Paragraph::new(e.as_str()).block(block),
+    };
+
+    let max_width = 10;
+    let mut lines = vec![];
+    for step in steps {
+        lines.push(Line::styled(
+            step.id.as_str(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        lines.push(style_job_field(
+            "State".to_string(),
+            step.state.clone(),
+            max_width,
+            theme.state_style(compact_state(&step.state)),
+        ));
+        for (field, value) in [
+            ("NodeList", &step.nodes),
+            ("Elapsed", &step.elapsed),
+            ("Command", &step.command),
+        ] {
+            lines.push(style_job_field(
+                field.to_string(),
+                value.clone(),
+                max_width,
+                Style::default(),
+            ));
+        }
+        lines.push(Line::raw(""));
+    }
+    Paragraph::new(Text::from(lines)).block(block)
+}
+
+// How the peak memory use of a job compares to what it asked for.
+// Close to the limit means it is about to run out of memory, far
+// below it means the request could be a lot smaller.
+fn get_mem_summary<'a>(usage: &[StepUsage], job: Option<&Job>) -> Line<'a> {
+    let peak = usage.iter().filter_map(|u| parse_mem(&u.max_rss)).max();
+    let requested = job.and_then(|j| get_mem_per_node(&j.TRES));
+    match (peak, requested) {
+        (Some(peak), Some(requested)) if requested > 0 => {
+            let percent = peak as f64 / requested as f64 * 100.0;
+            let style = if percent >= 90.0 {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else if percent < 25.0 {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            Line::styled(
+                format!(
+                    "Peak memory {} of {} per node ({:.0}%)",
+                    format_mem(peak),
+                    format_mem(requested),
+                    percent
+                ),
+                style,
+            )
+        }
+        (Some(peak), _) => Line::raw(format!("Peak memory {}", format_mem(peak))),
+        _ => Line::raw("Peak memory unknown"),
+    }
+}
+
+fn get_usage_details<'a>(
+    job_id: &str,
+    usage: &'a Result<Vec<StepUsage>, String>,
+    job: Option<&Job>,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Resource usage of {}", job_id));
+    let usage = match usage {
+        Ok(usage) if usage.is_empty() => {
+            return Paragraph::new("No usage data, is the job running?").block(block)
+        }
+        Ok(usage) => usage,
+        Err(e) => return Paragraph::new(e.as_str()).block(block),
+    };
+
+    let max_width = 12;
+    let mut lines = vec![get_mem_summary(usage, job), Line::raw("")];
+    for step in usage {
+        lines.push(Line::styled(
+            step.id.as_str(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        for (field, value) in [
+            ("AveCPU", &step.ave_cpu),
+            ("MaxRSS", &step.max_rss),
+            ("AveRSS", &step.ave_rss),
+            ("MaxDiskRead", &step.max_disk_read),
+            ("MaxDiskWrite", &step.max_disk_write),
+            ("TRESUsage", &step.tres_usage),
+        ] {
+            lines.push(style_job_field(
+                field.to_string(),
+                value.clone(),
+                max_width,
+                Style::default(),
+            ));
+        }
+        lines.push(Line::raw(""));
+    }
+    Paragraph::new(Text::from(lines))
+        .wrap(Wrap { trim: false })
+        .block(block)
+}
+
+// A job in the dependency graph, with its name and state if it is
+// still in the queue. Jobs which can never start because of a failed
+// dependency are highlighted, as is anything waiting on them.
+fn get_dependency_job<'a>(job_id: &str, jobs: &[&Job], theme: &Theme) -> Vec<Span<'a>> {
+    let mut spans = vec![Span::styled(
+        job_id.to_string(),
+        Style::default().fg(Color::Yellow),
+    )];
+    match jobs.iter().find(|j| refers_to(job_id, j)) {
+        Some(job) => {
+            spans.push(Span::raw(format!(" {} ", job.Name)));
+            spans.push(Span::styled(
+                job.StateCompact.clone(),
+                theme.state_style(&job.StateCompact),
+            ));
+            if job.Reason == "DependencyNeverSatisfied" {
+                spans.push(Span::styled(
+                    " DependencyNeverSatisfied",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ));
+            }
+        }
+        None => spans.push(Span::styled(
+            " (no longer queued)",
+            Style::default().fg(Color::Gray),
+        )),
+    }
+    spans
+}
+
+fn get_dependency_lines<'a>(
+    nodes: &[DependencyNode],
+    jobs: &[&Job],
+    theme: &Theme,
+    depth: usize,
+    lines: &mut Vec<Line<'a>>,
+) {
+    for node in nodes {
+        let (status, style) = match node.status {
+            DependencyStatus::Waiting => ("waiting", Style::default().fg(Color::Yellow)),
+            DependencyStatus::Failed => ("failed", Style::default().fg(Color::Red)),
+            DependencyStatus::Satisfied => ("satisfied", Style::default().fg(Color::Green)),
+        };
+        let mut spans = vec![
+            Span::raw(format!("{}└ ", "  ".repeat(depth))),
+            Span::styled(format!("[{}] ", status), style),
+            Span::raw(format!("{} ", node.kind)),
+        ];
+        spans.extend(get_dependency_job(&node.job_id, jobs, theme));
+        lines.push(Line::from(spans));
+        get_dependency_lines(&node.children, jobs, theme, depth + 1, lines);
+    }
+}
+
+fn get_dependency_graph<'a>(
+    job_id: &str,
+    cluster: &str,
+    jobs: &[Job],
+    theme: &Theme,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Dependencies of {}", job_id));
+    // a job can only depend on jobs of its own cluster,
+    // where job IDs are counted separately
+    let jobs: Vec<&Job> = jobs.iter().filter(|j| j.Cluster == cluster).collect();
+    let jobs = jobs.as_slice();
+    let Some(job) = jobs.iter().find(|j| j.JobID == job_id) else {
+        return Paragraph::new("This job is no longer in the queue.").block(block);
+    };
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::styled("Waiting on", bold)];
+    let upstream = get_upstream(job, jobs);
+    if upstream.is_empty() {
+        lines.push(Line::raw("  nothing"));
+    }
+    get_dependency_lines(&upstream, jobs, theme, 1, &mut lines);
+
+    lines.push(Line::raw(""));
+    lines.push(Line::from(get_dependency_job(job_id, jobs, theme)).patch_style(bold));
+    lines.push(Line::raw(""));
+
+    lines.push(Line::styled("Waited on by", bold));
+    let downstream = get_downstream(job, jobs);
+    if downstream.is_empty() {
+        lines.push(Line::raw("  nothing"));
+    }
+    get_dependency_lines(&downstream, jobs, theme, 1, &mut lines);
+
+    Paragraph::new(Text::from(lines)).block(block)
+}
+
+fn get_job_info<'a>(
+    job_id: &str,
+    info: &'a Result<Vec<(String, String)>, String>,
+    scroll: u16,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("scontrol show job {}", job_id));
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => return Paragraph::new(e.as_str()).block(block),
+    };
+
+    let max_width = info.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    let lines: Vec<Line> = info
+        .iter()
+        .map(|(k, v)| style_job_field(k.clone(), v.clone(), max_width, Style::default()))
+        .collect();
+    Paragraph::new(Text::from(lines))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(block)
+}
+
+// Highlight the #SBATCH directives, which are what we usually
+// want to know about when looking at someone's job script.
+fn highlight_script_line(line: &str) -> Line<'_> {
+    if let Some(options) = line.strip_prefix("#SBATCH") {
+        return Line::from(vec![
+            Span::styled("#SBATCH", Style::default().fg(Color::Magenta)),
+            Span::styled(options, Style::default().fg(Color::Cyan)),
+        ]);
+    }
+    match line.trim_start().starts_with('#') {
+        true => Line::styled(line, Style::default().fg(Color::DarkGray)),
+        false => Line::raw(line),
+    }
+}
+
+fn get_batch_script<'a>(
+    job_id: &str,
+    script: &'a Result<String, String>,
+    scroll: u16,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Batch script of {}", job_id));
+    let script = match script {
+        Ok(script) => script,
+        Err(e) => return Paragraph::new(e.as_str()).block(block),
+    };
+    let lines: Vec<Line> = script.lines().map(highlight_script_line).collect();
+    Paragraph::new(Text::from(lines))
+        .scroll((scroll, 0))
+        .block(block)
+}
+
+// Scrolled up `scroll` lines from the end, which
+// is where a running job's latest output shows up
+fn get_log<'a>(
+    path: &str,
+    log: &'a Result<String, String>,
+    scroll: u16,
+    height: u16,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("tail {}", path));
+    let log = match log {
+        Ok(log) if log.is_empty() => return Paragraph::new("Nothing written yet.").block(block),
+        Ok(log) => log,
+        Err(e) => {
+            return Paragraph::new(e.as_str())
+                .wrap(Wrap { trim: true })
+                .block(block)
+        }
+    };
+    let lines = log.lines().count() as u16;
+    let top = lines.saturating_sub(height.saturating_sub(2) + scroll);
+    Paragraph::new(log.as_str()).scroll((top, 0)).block(block)
+}
+
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn draw_form(f: &mut Frame, form: &mut Form) {
+    let label_width = form
+        .fields
+        .iter()
+        .map(|field| field.label.len())
+        .max()
+        .unwrap_or(0);
+    let area = centered_rect(80, form.fields.len() as u16 + 4, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Color::Green)
+        .title(form.title.as_str())
+        .title_bottom(match form.fields.len() {
+            0 | 1 => "<enter>: confirm | <esc>: back",
+            _ => "<enter>: submit | <tab>: next field | <esc>: cancel",
+        });
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let mut constraints = vec![Constraint::Length(1); form.fields.len()];
+    constraints.push(Constraint::Fill(1));
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .margin(1)
+        .split(inner);
+
+    for (i, field) in form.fields.iter_mut().enumerate() {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(label_width as u16 + 1),
+                Constraint::Fill(1),
+            ])
+            .split(rows[i]);
+        f.render_widget(
+            Paragraph::new(field.label.as_str()).style(Style::default().fg(Color::Yellow)),
+            columns[0],
+        );
+        let cursor_style = match i == form.focus {
+            true => Style::default().add_modifier(Modifier::REVERSED),
+            false => Style::default(),
+        };
+        field.input.set_cursor_style(cursor_style);
+        field.input.set_cursor_line_style(Style::default());
+        f.render_widget(field.input.widget(), columns[1]);
+    }
 }
 
 pub fn draw(f: &mut Frame, app: &mut App) {
@@ -192,36 +1110,65 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     let pkg_version = env!("CARGO_PKG_VERSION");
     let pkg_authors = env!("CARGO_PKG_AUTHORS");
     let pkg_repo = env!("CARGO_PKG_REPOSITORY");
-    f.render_widget(
-        Paragraph::new(format!(
-            "{} v{} | {} | {}",
-            pkg_name, pkg_version, pkg_authors, pkg_repo
-        )),
-        outer_layout[0],
-    );
+    // with several clusters the header becomes their tabs
+    if app.clusters.len() > 1 {
+        let titles = std::iter::once("All").chain(app.clusters.iter().map(|c| c.name.as_str()));
+        f.render_widget(
+            Tabs::new(titles)
+                .select(app.cluster_tab)
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            outer_layout[0],
+        );
+    } else {
+        f.render_widget(
+            Paragraph::new(format!(
+                "{} v{} | {} | {}",
+                pkg_name, pkg_version, pkg_authors, pkg_repo
+            )),
+            outer_layout[0],
+        );
+    }
+    let show_cluster = app.clusters.len() > 1 && app.cluster_tab == 0;
 
+    let jobs_title = match &app.reason_filter {
+        Some(reason) => format!("Jobs (pending: {})", reason),
+        None if app.show_finished => "Jobs (and finished)".to_string(),
+        None => "Jobs".to_string(),
+    };
     match app.editor_state {
-        EditorState::Editing => {
+        EditorState::Editing | EditorState::Form => {
             f.render_stateful_widget(
-                List::new(get_short_jobs_list(&app.jobs))
-                    .block(Block::new().borders(Borders::ALL).title_top("Jobs"))
-                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
-                    .repeat_highlight_symbol(true),
+                List::new(get_short_jobs_list(
+                    &app.rows,
+                    &app.jobs,
+                    &app.start_estimates,
+                    &app.config,
+                    show_cluster,
+                ))
+                .block(Block::new().borders(Borders::ALL).title_top(jobs_title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .repeat_highlight_symbol(true),
                 inner_layout[0],
                 &mut app.list_state,
             );
         }
         EditorState::Normal => {
             f.render_stateful_widget(
-                List::new(get_short_jobs_list(&app.jobs))
-                    .block(
-                        Block::new()
-                            .borders(Borders::ALL)
-                            .title_top("Jobs")
-                            .border_style(Color::Green),
-                    )
-                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
-                    .repeat_highlight_symbol(true),
+                List::new(get_short_jobs_list(
+                    &app.rows,
+                    &app.jobs,
+                    &app.start_estimates,
+                    &app.config,
+                    show_cluster,
+                ))
+                .block(
+                    Block::new()
+                        .borders(Borders::ALL)
+                        .title_top(jobs_title)
+                        .border_style(Color::Green),
+                )
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .repeat_highlight_symbol(true),
                 inner_layout[0],
                 &mut app.list_state,
             );
@@ -230,13 +1177,22 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
     match app.view_state {
         ViewState::Overview => {
-            get_user_stats(f, inner_layout[1], &app.overview);
+            get_group_stats(
+                f,
+                inner_layout[1],
+                &app.overview,
+                &app.config,
+                &app.user_efficiency,
+            );
         }
-        ViewState::Details => match app.list_state.selected() {
-            Some(i) => {
-                let job = &app.jobs[i];
+        ViewState::Details => match app.list_state.selected().map(|i| &app.rows[i]) {
+            Some(JobRow::Job(i)) | Some(JobRow::ArrayTask(i)) => {
+                draw_job_details(f, inner_layout[1], &app.jobs[*i], app);
+            }
+            Some(JobRow::Array { id, tasks, .. }) => {
+                let tasks: Vec<&Job> = tasks.iter().map(|t| &app.jobs[*t]).collect();
                 f.render_widget(
-                    get_job_details(&job)
+                    get_array_details(id, &tasks)
                         .block(Block::new().borders(Borders::ALL).title_top("Details")),
                     inner_layout[1],
                 );
@@ -252,14 +1208,64 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 );
             }
         },
+        ViewState::Steps => f.render_widget(
+            get_steps_details(&app.inspected_job_id, &app.steps, &app.config.theme),
+            inner_layout[1],
+        ),
+        ViewState::Usage => f.render_widget(
+            get_usage_details(&app.inspected_job_id, &app.usage, app.inspected_job()),
+            inner_layout[1],
+        ),
+        ViewState::Dependencies => f.render_widget(
+            get_dependency_graph(
+                &app.inspected_job_id,
+                &app.inspected_cluster,
+                &app.all_jobs,
+                &app.config.theme,
+            ),
+            inner_layout[1],
+        ),
+        ViewState::Info => f.render_widget(
+            get_job_info(&app.inspected_job_id, &app.job_info, app.panel_scroll),
+            inner_layout[1],
+        ),
+        ViewState::Script => f.render_widget(
+            get_batch_script(&app.inspected_job_id, &app.batch_script, app.panel_scroll),
+            inner_layout[1],
+        ),
+        ViewState::Log => f.render_widget(
+            get_log(
+                &app.log_path,
+                &app.log,
+                app.panel_scroll,
+                inner_layout[1].height,
+            ),
+            inner_layout[1],
+        ),
+        ViewState::Reasons => {
+            get_reason_stats(f, inner_layout[1], &app.reasons, &mut app.reason_state);
+        }
+        ViewState::Partitions => {
+            f.render_widget(get_partition_stats(&app.partitions), inner_layout[1]);
+        }
+        ViewState::Templates => {
+            get_templates(f, inner_layout[1], &app.templates, &mut app.template_state);
+        }
         ViewState::Help => f.render_widget(
-            Paragraph::new(HELP).block(Block::new().borders(Borders::ALL).title_top("Help")),
+            Paragraph::new(HELP)
+                .wrap(Wrap { trim: false })
+                .scroll((app.panel_scroll, 0))
+                .block(
+                    Block::new()
+                        .borders(Borders::ALL)
+                        .title_top("Help (scroll with j/k)"),
+                ),
             inner_layout[1],
         ),
     }
 
     match app.editor_state {
-        EditorState::Normal => {
+        EditorState::Normal | EditorState::Form => {
             app.text_area.set_block(
                 Block::new()
                     .borders(Borders::ALL)
@@ -279,8 +1285,500 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         }
     }
     f.render_widget(app.text_area.widget(), outer_layout[2]);
-    f.render_widget(Paragraph::new(HELP_SHORT), outer_layout[3]);
+
+    match &app.status {
+        Some(Ok(message)) => f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Green)),
+            outer_layout[3],
+        ),
+        Some(Err(message)) => f.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            outer_layout[3],
+        ),
+        None => f.render_widget(Paragraph::new(HELP_SHORT), outer_layout[3]),
+    }
+
+    if let Some((_, form)) = &mut app.form {
+        draw_form(f, form);
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{Action, FormPurpose};
+    use crate::backend::{simulated, Backend, Cluster};
+    use crate::config::SimulatorConfig;
+    use crate::jobs::is_finished;
+    use crate::steps::Step;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::sync::Arc;
 
+    // Serves a fixed list of jobs, and nothing else
+    struct Fixture(Vec<Job>);
 
+    impl Backend for Fixture {
+        fn get_jobs(&self, finished: bool) -> Result<Vec<Job>, String> {
+            let mut jobs = self.0.clone();
+            jobs.retain(|j| finished || !is_finished(&j.State));
+            Ok(jobs)
+        }
+        fn get_steps(&self, _: &str, _: bool) -> Result<Vec<Step>, String> {
+            Err("no steps in the fixture".to_string())
+        }
+        fn get_usage(&self, _: &str) -> Result<Vec<StepUsage>, String> {
+            Err("no usage in the fixture".to_string())
+        }
+        fn get_job_efficiency(&self, job_id: &str) -> Result<Efficiency, String> {
+            match self.0.iter().find(|j| j.JobID == job_id) {
+                Some(j) if is_finished(&j.State) => Ok(Efficiency {
+                    cpu_used: TimeDelta::hours(3),
+                    cpu_allocated: TimeDelta::hours(8),
+                    mem_used: 24 << 30,
+                    mem_requested: 32 << 30,
+                }),
+                _ => Err("no efficiency in the fixture".to_string()),
+            }
+        }
+        fn get_user_efficiency(&self) -> Result<HashMap<String, Efficiency>, String> {
+            Ok(HashMap::new())
+        }
+        fn show_job(&self, _: &str) -> Result<Vec<(String, String)>, String> {
+            Err("no details in the fixture".to_string())
+        }
+        fn get_batch_script(&self, _: &str) -> Result<String, String> {
+            Ok("#!/bin/sh\n./run.sh\n".to_string())
+        }
+        fn update_job(&self, _: &str, _: &[(String, String)]) -> Result<(), String> {
+            Err("the fixture is read only".to_string())
+        }
+        fn submit(&self, _: &str, _: &[(String, String)]) -> Result<String, String> {
+            Err("the fixture is read only".to_string())
+        }
+        fn current_user(&self) -> Result<String, String> {
+            Ok("alice".to_string())
+        }
+        fn cancel_job(&self, _: &str) -> Result<(), String> {
+            Err("the fixture is read only".to_string())
+        }
+        fn hold_job(&self, _: &str) -> Result<(), String> {
+            Err("the fixture is read only".to_string())
+        }
+        fn release_job(&self, _: &str) -> Result<(), String> {
+            Err("the fixture is read only".to_string())
+        }
+        fn get_log(&self, job_id: &str, _: &str) -> Result<String, String> {
+            let lines: Vec<String> = (1..=30).map(|i| format!("{} line {}", job_id, i)).collect();
+            Ok(lines.join("\n"))
+        }
+        fn get_partitions(&self) -> Result<Vec<Partition>, String> {
+            let partition = |name: &str, state: &str, nodes, cpus| Partition {
+                cluster: String::new(),
+                name: name.to_string(),
+                state: state.to_string(),
+                nodes,
+                cpus,
+            };
+            let counts = |allocated, idle, other, total| Counts {
+                allocated,
+                idle,
+                other,
+                total,
+            };
+            Ok(vec![
+                partition("gpu*", "up", counts(2, 1, 1, 4), counts(80, 48, 64, 192)),
+                partition("debug", "down", counts(0, 0, 1, 1), counts(0, 0, 8, 8)),
+            ])
+        }
+    }
+
+    // Times are fixed strings, and pending jobs have no start estimate,
+    // so that nothing drawn depends on the clock.
+    fn job(id: &str, user: &str, state: &str, partition: &str, node: &str, name: &str) -> Job {
+        let running = state == "RUNNING";
+        Job {
+            StateCompact: compact_state(state).to_string(),
+            State: state.to_string(),
+            Reason: if running { "None" } else { "Resources" }.to_string(),
+            Name: name.to_string(),
+            UserName: user.to_string(),
+            Account: "research".to_string(),
+            QOS: "normal".to_string(),
+            Cluster: String::new(),
+            JobID: id.to_string(),
+            ArrayJobID: id.to_string(),
+            ArrayTaskID: "N/A".to_string(),
+            Partition: partition.to_string(),
+            NodeList: node.to_string(),
+            ReqNodes: String::new(),
+            ExcNodes: String::new(),
+            SubmitTime: "2024-05-01T09:00:00".to_string(),
+            StartTime: if running {
+                "2024-05-01T09:30:00"
+            } else {
+                "N/A"
+            }
+            .to_string(),
+            TimeLimit: "4:00:00".to_string(),
+            TimeUsed: if running { "1:15:00" } else { "0:00" }.to_string(),
+            TRES: "cpu=8,mem=32G,node=1,billing=8,gres/gpu:a100=2".to_string(),
+            Dependency: String::new(),
+            NumTasks: "1".to_string(),
+            Priority: "10000".to_string(),
+            Nice: "0".to_string(),
+            WorkDir: format!("/home/{}", user),
+            Command: format!("/home/{}/run.sh", user),
+            STDOUT: format!("/home/{}/slurm-{}.out", user, id),
+            STDERR: format!("/home/{}/slurm-{}.out", user, id),
+        }
+    }
+
+    fn jobs() -> Vec<Job> {
+        vec![
+            job("101", "alice", "RUNNING", "gpu", "node01", "train-resnet"),
+            job(
+                "102",
+                "bob",
+                "RUNNING",
+                "gpu",
+                "node02",
+                "a very long job name which will not fit",
+            ),
+            job("103", "alice", "PENDING", "debug", "", "eval"),
+            job("104", "carol", "PENDING", "gpu", "", "preprocess"),
+        ]
+    }
+
+    fn app(jobs: Vec<Job>) -> App<'static> {
+        let cluster = Cluster {
+            name: String::new(),
+            backend: Arc::new(Fixture(jobs)),
+        };
+        App::new(Config::default(), vec![cluster])
+    }
+
+    fn draw_app(app: &mut App, width: u16, height: u16) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        terminal
+    }
+
+    fn render(app: &mut App, width: u16, height: u16) -> String {
+        draw_app(app, width, height).backend().to_string()
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            app.update(Some(Action::InputKey(key)));
+        }
+    }
+
+    #[test]
+    fn empty_list() {
+        let mut app = app(vec![]);
+        // moving around and opening panels with nothing selected
+        for action in [
+            Action::Down,
+            Action::Up,
+            Action::End,
+            Action::Home,
+            Action::Select,
+            Action::ToggleArray,
+            Action::ToggleSteps,
+            Action::ToggleInfo,
+            Action::EditJob,
+            Action::ToggleOverview,
+        ] {
+            app.update(Some(action));
+        }
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn overview() {
+        let mut app = app(jobs());
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn overview_by_partition() {
+        let mut app = app(jobs());
+        app.update(Some(Action::CycleGroupBy));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn help() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleHelp));
+        insta::assert_snapshot!(render(&mut app, 120, 30));
+    }
+
+    #[test]
+    fn help_scrolled() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleHelp));
+        for _ in 0..20 {
+            app.update(Some(Action::PageDown));
+        }
+        insta::assert_snapshot!(render(&mut app, 120, 30));
+    }
+
+    #[test]
+    fn details() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleOverview));
+        insta::assert_snapshot!(render(&mut app, 120, 40));
+    }
+
+    #[test]
+    fn details_of_pending_job() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleOverview));
+        app.update(Some(Action::End));
+        insta::assert_snapshot!(render(&mut app, 120, 40));
+    }
+
+    #[test]
+    fn details_of_finished_job() {
+        let mut jobs = jobs();
+        jobs.push(job("100", "erin", "TIMEOUT", "gpu", "node03", "finetune"));
+        let mut app = app(jobs);
+        assert_eq!(app.jobs.len(), 4);
+        app.update(Some(Action::ToggleFinished));
+        app.update(Some(Action::End));
+        app.update(Some(Action::Tick));
+        insta::assert_snapshot!(render(&mut app, 120, 40));
+    }
+
+    // the end of the output, where the latest lines are
+    #[test]
+    fn log() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleStdout));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+        app.update(Some(Action::Up));
+        app.update(Some(Action::Up));
+        insta::assert_snapshot!("log_scrolled", render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn partitions() {
+        let mut app = app(jobs());
+        app.update(Some(Action::TogglePartitions));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn editing_filter() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleFocus));
+        type_text(&mut app, "alice");
+        app.update(Some(Action::Tick));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn edit_form() {
+        let mut app = app(jobs());
+        app.update(Some(Action::End));
+        app.update(Some(Action::EditJob));
+        insta::assert_snapshot!(render(&mut app, 120, 30));
+    }
+
+    #[test]
+    fn steps_of_array() {
+        let mut jobs = jobs();
+        for task in ["0", "1", "2"] {
+            let mut j = job(
+                &format!("200_{}", task),
+                "dave",
+                "PENDING",
+                "gpu",
+                "",
+                "sweep",
+            );
+            (j.ArrayJobID, j.ArrayTaskID) = ("200".to_string(), task.to_string());
+            jobs.push(j);
+        }
+        let mut app = app(jobs);
+        app.update(Some(Action::End));
+        app.update(Some(Action::ToggleSteps));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    // job IDs are counted separately on each cluster, so
+    // the same array ID is a different array on each
+    #[test]
+    fn same_array_on_two_clusters() {
+        let cluster = |name: &str, user: &str| {
+            let tasks = ["0", "1"].map(|task| {
+                let mut j = job(
+                    &format!("200_{}", task),
+                    user,
+                    "PENDING",
+                    "gpu",
+                    "",
+                    "sweep",
+                );
+                (j.ArrayJobID, j.ArrayTaskID) = ("200".to_string(), task.to_string());
+                j
+            });
+            Cluster {
+                name: name.to_string(),
+                backend: Arc::new(Fixture(tasks.to_vec())),
+            }
+        };
+        let clusters = vec![cluster("alpha", "alice"), cluster("beta", "bob")];
+        let mut app = App::new(Config::default(), clusters);
+        app.update(Some(Action::ToggleArray));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+    }
+
+    #[test]
+    fn resubmit_form() {
+        let mut jobs = jobs();
+        let mut j = job("100", "erin", "TIMEOUT", "gpu", "node[03-04]", "finetune");
+        j.TRES = "cpu=16,mem=64G,node=2,billing=16,gres/gpu:a100=4".to_string();
+        jobs.push(j);
+        let mut app = app(jobs);
+        app.update(Some(Action::ToggleFinished));
+        app.update(Some(Action::End));
+        app.update(Some(Action::ResubmitJob));
+        insta::assert_snapshot!(render(&mut app, 120, 40));
+    }
+
+    fn filter(app: &mut App, filter: &str) {
+        app.update(Some(Action::ToggleFocus));
+        type_text(app, filter);
+        app.update(Some(Action::ToggleFocus));
+        app.update(Some(Action::Tick));
+    }
+
+    fn type_in_form(app: &mut App, text: &str) {
+        for c in text.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            app.update(Some(Action::FormInput(key)));
+        }
+    }
+
+    // the fixture runs as alice, so bob's and carol's jobs are left alone
+    #[test]
+    fn cancel_listed_jobs() {
+        let mut app = app(jobs());
+        filter(&mut app, "gpu");
+        app.update(Some(Action::CancelListedJobs));
+        insta::assert_snapshot!(render(&mut app, 120, 20));
+        let Some((FormPurpose::Cancel { jobs }, _)) = &app.form else {
+            panic!("expected a confirmation");
+        };
+        let job_ids: Vec<&str> = jobs.iter().map(|(_, id)| id.as_str()).collect();
+        assert_eq!(job_ids, ["101", "103"]);
+    }
+
+    #[test]
+    fn cancel_needs_a_filter() {
+        for text in ["", "train("] {
+            let mut app = app(jobs());
+            filter(&mut app, text);
+            app.update(Some(Action::CancelListedJobs));
+            assert!(app.form.is_none());
+            assert_eq!(
+                app.status,
+                Some(Err(
+                    "Filter the list down to the jobs to cancel first".to_string()
+                ))
+            );
+        }
+    }
+
+    // what the simulator is for: trying out bulk cancel on a made up queue
+    // shared with other users
+    #[test]
+    fn bulk_cancel_on_simulator() {
+        let config = SimulatorConfig {
+            seed: Some(1),
+            ..SimulatorConfig::default()
+        };
+        let clusters = simulated(&config);
+        let backend = clusters[0].backend.clone();
+        let you = backend.current_user().unwrap();
+        let script = "#!/bin/bash\n#SBATCH --job-name=sweep\n./sweep.sh\n";
+        for _ in 0..3 {
+            backend.submit(script, &[]).unwrap();
+        }
+        let mut app = App::new(Config::default(), clusters);
+        filter(&mut app, "sweep|a");
+        let yours = |app: &App| {
+            app.jobs
+                .iter()
+                .filter(|j| !is_finished(&j.State) && j.UserName == you)
+                .count()
+        };
+        let others = app.jobs.len() - yours(&app);
+        assert!(yours(&app) >= 3 && others > 0);
+
+        // nothing happens until the number of jobs is typed in
+        app.update(Some(Action::CancelListedJobs));
+        type_in_form(&mut app, "2");
+        app.update(Some(Action::SubmitForm));
+        assert!(matches!(app.status, Some(Err(_))));
+        assert_eq!(app.jobs.len() - others, yours(&app));
+
+        let count = yours(&app);
+        app.update(Some(Action::CancelListedJobs));
+        type_in_form(&mut app, &count.to_string());
+        app.update(Some(Action::SubmitForm));
+        assert_eq!(app.status, Some(Ok(format!("Cancelled {} jobs", count))));
+        assert_eq!(yours(&app), 0);
+        assert_eq!(app.jobs.len(), others);
+        app.update(Some(Action::ToggleFinished));
+        assert!(app.jobs.iter().any(|j| j.State == "CANCELLED"));
+    }
+
+    #[test]
+    fn hold_asks_first() {
+        let config = SimulatorConfig {
+            seed: Some(1),
+            ..SimulatorConfig::default()
+        };
+        let mut app = App::new(Config::default(), simulated(&config));
+        filter(&mut app, "PENDING");
+        let (job_id, _) = app.selected_target().unwrap();
+        app.update(Some(Action::HoldJob));
+        app.update(Some(Action::ResetView));
+        assert!(app.status.is_none());
+        app.update(Some(Action::HoldJob));
+        app.update(Some(Action::SubmitForm));
+        assert_eq!(app.status, Some(Ok(format!("Held job {}", job_id))));
+    }
+
+    #[test]
+    fn narrow() {
+        let mut app = app(jobs());
+        insta::assert_snapshot!(render(&mut app, 50, 20));
+    }
+
+    #[test]
+    fn narrow_details() {
+        let mut app = app(jobs());
+        app.update(Some(Action::ToggleOverview));
+        insta::assert_snapshot!(render(&mut app, 50, 30));
+    }
+
+    // nothing to compare, as long as it doesn't panic
+    #[test]
+    fn tiny() {
+        let mut app = app(jobs());
+        for view in [Action::ToggleOverview, Action::ToggleHelp, Action::EditJob] {
+            app.update(Some(view));
+            for width in 0..20 {
+                for height in 0..8 {
+                    draw_app(&mut app, width, height);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use chrono::TimeDelta;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::command::Runner;
+use crate::time::parse_duration;
+
+// Resource usage of a running job step, as reported by sstat.
+pub struct StepUsage {
+    pub id: String,
+    pub ave_cpu: String,
+    pub max_rss: String,
+    pub ave_rss: String,
+    pub max_disk_read: String,
+    pub max_disk_write: String,
+    pub tres_usage: String,
+}
+
+pub fn get_usage(runner: &Runner, job_id: &str) -> Result<Vec<StepUsage>, String> {
+    // sstat only knows about the cluster it is run on, and has no
+    // --clusters to ask another one, so it would find the wrong job
+    if let Some(cluster) = &runner.cluster {
+        return Err(format!(
+            "Usage of running jobs is not available for cluster {}: sstat has no --clusters option",
+            cluster
+        ));
+    }
+    let output = runner.run(
+        "sstat",
+        &[
+            "--noheader",
+            "--parsable2",
+            "--allsteps",
+            "--jobs",
+            job_id,
+            "--format",
+            "JobID,AveCPU,MaxRSS,AveRSS,MaxDiskRead,MaxDiskWrite,TRESUsageInTot",
+        ],
+    )?;
+
+    let usage = output
+        .lines()
+        .filter_map(|l| {
+            let parts: Vec<&str> = l.split('|').collect();
+            match parts[..] {
+                [id, ave_cpu, max_rss, ave_rss, max_disk_read, max_disk_write, tres_usage] => {
+                    Some(StepUsage {
+                        id: id.to_string(),
+                        ave_cpu: ave_cpu.to_string(),
+                        max_rss: max_rss.to_string(),
+                        ave_rss: ave_rss.to_string(),
+                        max_disk_read: max_disk_read.to_string(),
+                        max_disk_write: max_disk_write.to_string(),
+                        tres_usage: tres_usage.to_string(),
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(usage)
+}
+
+// Slurm memory sizes are a number with an optional K/M/G/T/P suffix,
+// e.g. 1234K or 16G. Without a suffix the size is in bytes.
+pub fn parse_mem(mem: &str) -> Option<u64> {
+    let mem = mem.trim();
+    let (number, multiplier): (&str, u64) = match mem.chars().last()? {
+        'K' => (&mem[..mem.len() - 1], 1 << 10),
+        'M' => (&mem[..mem.len() - 1], 1 << 20),
+        'G' => (&mem[..mem.len() - 1], 1 << 30),
+        'T' => (&mem[..mem.len() - 1], 1 << 40),
+        'P' => (&mem[..mem.len() - 1], 1 << 50),
+        _ => (mem, 1),
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * multiplier as f64) as u64)
+}
+
+pub fn format_mem(bytes: u64) -> String {
+    let units = ["B", "K", "M", "G", "T", "P"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{}{}", bytes, units[0]),
+        _ => format!("{:.1}{}", size, units[unit]),
+    }
+}
+
+static MEM_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|,)mem=([\d.]+[KMGTP]?)").unwrap());
+static NODE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|,)node=(\d+)").unwrap());
+
+// The memory a job was allocated on each of its nodes, from its TRES,
+// e.g. cpu=4,mem=16G,node=1,billing=4.
+pub fn get_mem_per_node(tres: &str) -> Option<u64> {
+    let mem = parse_mem(&MEM_RE.captures(tres)?[1])?;
+    Some(mem / get_node_count(tres)?)
+}
+
+// How many nodes a job has (or asked for), from its TRES
+pub fn get_node_count(tres: &str) -> Option<u64> {
+    match NODE_RE.captures(tres) {
+        Some(caps) => caps[1].parse().ok().filter(|n| *n > 0),
+        None => Some(1),
+    }
+}
+
+// How much of the CPU time and memory a finished job was allocated
+// it actually used, in the manner of seff. Memory is compared per
+// node, as MaxRSS is the peak of any single task.
+#[derive(Clone, Debug, Default)]
+pub struct Efficiency {
+    pub cpu_used: TimeDelta,
+    pub cpu_allocated: TimeDelta,
+    pub mem_used: u64,
+    pub mem_requested: u64,
+}
+
+impl Efficiency {
+    pub fn cpu_percent(&self) -> Option<f64> {
+        match self.cpu_allocated.num_seconds() {
+            0 => None,
+            allocated => Some(self.cpu_used.num_seconds() as f64 / allocated as f64 * 100.0),
+        }
+    }
+
+    pub fn mem_percent(&self) -> Option<f64> {
+        match self.mem_requested {
+            0 => None,
+            requested => Some(self.mem_used as f64 / requested as f64 * 100.0),
+        }
+    }
+
+    pub fn add(&mut self, other: &Efficiency) {
+        self.cpu_used += other.cpu_used;
+        self.cpu_allocated += other.cpu_allocated;
+        self.mem_used += other.mem_used;
+        self.mem_requested += other.mem_requested;
+    }
+}
+
+const EFFICIENCY_FORMAT: &str = "JobID,User,Elapsed,TotalCPU,AllocCPUS,MaxRSS,ReqMem,NNodes";
+
+pub fn get_job_efficiency(runner: &Runner, job_id: &str) -> Result<Efficiency, String> {
+    let output = runner.run(
+        "sacct",
+        &[
+            "--noheader",
+            "--parsable2",
+            "--jobs",
+            job_id,
+            "--format",
+            EFFICIENCY_FORMAT,
+        ],
+    )?;
+    parse_efficiency(&output)
+        .remove(job_id)
+        .map(|(_, efficiency)| efficiency)
+        .ok_or(format!("sacct has no record of job {}", job_id))
+}
+
+// Efficiency of each user's jobs which finished in the last day
+pub fn get_user_efficiency(runner: &Runner) -> Result<HashMap<String, Efficiency>, String> {
+    let output = runner.run(
+        "sacct",
+        &[
+            "--allusers",
+            "--noheader",
+            "--parsable2",
+            "--starttime",
+            "now-1days",
+            "--state",
+            "CD,F,TO,OOM,CA",
+            "--format",
+            EFFICIENCY_FORMAT,
+        ],
+    )?;
+    let mut user_efficiency: HashMap<String, Efficiency> = HashMap::new();
+    for (user, efficiency) in parse_efficiency(&output).into_values() {
+        user_efficiency.entry(user).or_default().add(&efficiency);
+    }
+    Ok(user_efficiency)
+}
+
+// sacct prints a line for each job, followed by a line for each of
+// its steps. CPU time and the memory request are on the job's line,
+// but MaxRSS is only on the lines of its steps.
+fn parse_efficiency(output: &str) -> HashMap<String, (String, Efficiency)> {
+    let mut jobs: HashMap<String, (String, Efficiency)> = HashMap::new();
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split('|').collect();
+        let [id, user, elapsed, total_cpu, cpus, max_rss, req_mem, nodes] = parts[..] else {
+            continue;
+        };
+        let (job_id, is_step) = match id.split_once('.') {
+            Some((job_id, _)) => (job_id, true),
+            None => (id, false),
+        };
+        let (_, efficiency) = jobs.entry(job_id.to_string()).or_default();
+
+        if is_step {
+            if let Some(rss) = parse_mem(max_rss) {
+                efficiency.mem_used = efficiency.mem_used.max(rss);
+            }
+            continue;
+        }
+
+        let cpus: i32 = cpus.parse().unwrap_or(0);
+        let nodes: u64 = nodes.parse().unwrap_or(1).max(1);
+        // TotalCPU has fractional seconds, e.g. 01:02.345
+        let total_cpu = total_cpu.split('.').next().unwrap_or("");
+        efficiency.cpu_used = parse_duration(total_cpu).unwrap_or_default();
+        efficiency.cpu_allocated = parse_duration(elapsed).unwrap_or_default() * cpus;
+        efficiency.mem_requested = parse_req_mem(req_mem, cpus as u64, nodes).unwrap_or(0);
+        jobs.get_mut(job_id).unwrap().0 = user.to_string();
+    }
+    jobs
+}
+
+// ReqMem is per node, or per CPU on older versions of Slurm, which
+// mark it with a trailing n or c respectively, e.g. 4000Mc.
+fn parse_req_mem(req_mem: &str, cpus: u64, nodes: u64) -> Option<u64> {
+    if let Some(per_cpu) = req_mem.strip_suffix('c') {
+        return Some(parse_mem(per_cpu)? * cpus / nodes);
+    }
+    parse_mem(req_mem.strip_suffix('n').unwrap_or(req_mem))
+}